@@ -1,4 +1,56 @@
+use crate::config::RedirectConfig;
+use crate::error::ConfigError;
+use crate::hint::{
+    Assume, AsyncSchemeResolver, ForwardedProtoPolicy, HostSource, SchemeHint, SchemeResolver,
+    SchemeSource,
+};
+use crate::matcher::{GlobPattern, HostPattern, PathPattern};
+use crate::policy::{
+    H2cPolicy, LocationEncoding, LocationSerializer, Profile, RedirectBody, RedirectHook,
+    RedirectPredicate, ReplacementScope, UrlMapper, WebSocketPolicy, WwwPolicy,
+};
+use crate::responder::RedirectResponder;
 use crate::scheme::RedirectScheme;
+use actix_web::{
+    dev::{ResourceDef, ServiceRequest},
+    guard::Guard,
+    http::{
+        header::{HeaderName, HeaderValue},
+        Method, StatusCode,
+    },
+    middleware::normalize::TrailingSlash,
+    HttpResponse,
+};
+use futures::future::{Future, FutureExt};
+use ipnet::IpNet;
+#[cfg(feature = "regex")]
+use regex::RegexSet;
+#[cfg(feature = "config")]
+use std::fs;
+use std::net::IpAddr;
+#[cfg(feature = "config")]
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Loopback and private networks, where reverse proxies usually live
+const PRIVATE_NETWORKS: &[&str] = &[
+    "127.0.0.0/8",
+    "10.0.0.0/8",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "::1/128",
+    "fc00::/7",
+];
+
+// Minimal HSTS max age accepted for preloading (one year)
+const HSTS_PRELOAD_MIN_MAX_AGE: Duration = Duration::from_secs(31_536_000);
+
+// Paths commonly probed by load balancers and orchestrators
+const HEALTH_CHECK_PATHS: &[&str] = &["/healthz", "/livez", "/readyz", "/ping", "/status"];
+
+// Maximal number of dynamic segments of an actix route
+const MAX_ROUTE_SEGMENTS: usize = 16;
 
 #[derive(Clone, Default)]
 pub struct RedirectSchemeBuilder {
@@ -10,6 +62,178 @@ pub struct RedirectSchemeBuilder {
     temporary: bool,
     // List of string replacements
     replacements: Vec<(String, String)>,
+    // Proxies allowed to set forwarded scheme headers (empty: trust everyone)
+    trusted_proxies: Vec<IpNet>,
+    // Prefer legacy `X-Forwarded-*` headers over RFC 7239 `Forwarded` header
+    prefer_x_forwarded: bool,
+    // Custom header carrying the original scheme
+    scheme_header: Option<String>,
+    // Read the original scheme from Cloudflare's `CF-Visitor` header
+    cf_visitor: bool,
+    // Header whose presence marks the request as HTTPS
+    https_header: Option<String>,
+    // Scheme assumed when the request does not reveal it
+    unknown_scheme_policy: Assume,
+    // Sources of the request scheme in order of priority (empty: default order)
+    scheme_sources: Vec<SchemeSource>,
+    // Handling of HTTP/2 cleartext requests
+    h2c_policy: H2cPolicy,
+    // Handling of WebSocket handshakes
+    websocket_policy: WebSocketPolicy,
+    // Interpretation of multi-valued `X-Forwarded-Proto` headers
+    forwarded_proto_policy: ForwardedProtoPolicy,
+    // Build the redirection host from `X-Forwarded-Host`
+    forwarded_host: bool,
+    // Synchronous resolver of the request scheme
+    scheme_resolver: Option<SchemeResolver>,
+    // Asynchronous resolver of the request scheme
+    async_scheme_resolver: Option<AsyncSchemeResolver>,
+    // Redirect only requests carrying `Upgrade-Insecure-Requests: 1`
+    upgrade_insecure_only: bool,
+    // Status code for requests carrying `Upgrade-Insecure-Requests: 1`
+    upgrade_insecure_status: Option<StatusCode>,
+    // Status code for requests not to be upgraded (None: pass through)
+    insecure_response: Option<StatusCode>,
+    // Port of the HTTP listener
+    http_port: Option<u16>,
+    // Port of the HTTPS listener
+    https_port: Option<u16>,
+    // Source of the host of the redirection target
+    host_source: HostSource,
+    // Permanent redirect preserving the method (true: 308 Permanent Redirect, false: 301 Moved Permanently)
+    preserve_method: bool,
+    // Answer code of redirections, overriding permanent and temporary flags
+    status_code: Option<StatusCode>,
+    // Answer code of redirections for unsafe methods like POST
+    unsafe_methods_status: Option<StatusCode>,
+    // Answer code and body rejecting requests instead of redirecting them
+    reject: Option<(StatusCode, String)>,
+    // Answer "426 Upgrade Required" instead of redirecting to HTTPS
+    upgrade_required: bool,
+    // Body of redirection responses
+    redirect_body: RedirectBody,
+    // Serializer of JSON redirection bodies
+    json_serializer: Option<LocationSerializer>,
+    // Additional headers of redirection responses
+    redirect_headers: Vec<(HeaderName, HeaderValue)>,
+    // `Cache-Control` header of redirections
+    cache_control: Option<HeaderValue>,
+    // Max age of the `Strict-Transport-Security` header added to HTTPS responses
+    hsts_max_age: Option<Duration>,
+    // Add `includeSubDomains` to the `Strict-Transport-Security` header
+    hsts_include_subdomains: bool,
+    // Add `preload` to the `Strict-Transport-Security` header
+    hsts_preload: bool,
+    // Add `Refresh` header to redirections
+    refresh_header: bool,
+    // Add `<meta http-equiv="refresh">` to HTML bodies of redirections
+    meta_refresh: bool,
+    // Add `Link: <...>; rel="canonical"` header to redirections
+    canonical_link: bool,
+    // Add `Link: <...>; rel="canonical"` header to responses passed through
+    canonical_link_pass_through: bool,
+    // Hook mutating generated responses before they are returned
+    on_redirect: Option<RedirectHook>,
+    // Largest request body redirected and answer code for larger ones
+    body_limit: Option<(u64, StatusCode)>,
+    // Producer of redirection responses (None: built-in)
+    responder: Option<Arc<dyn RedirectResponder>>,
+    // Methods of redirected requests (empty: all methods)
+    redirect_methods: Vec<Method>,
+    // Add `X-Redirected-By` header to generated responses
+    diagnostic_header: bool,
+    // Answer codes of redirections for path prefixes
+    path_statuses: Vec<(String, StatusCode)>,
+    // Paths never redirected
+    ignore_paths: Vec<PathPattern>,
+    // Actix routes of paths never redirected, compiled when building
+    ignore_path_routes: Vec<String>,
+    // Regular expressions of paths never redirected
+    #[cfg(feature = "regex")]
+    ignore_path_regexes: Vec<String>,
+    // Paths redirected exclusively (empty: all paths)
+    only_paths: Vec<PathPattern>,
+    // File extensions of paths never redirected
+    ignore_extensions: Vec<String>,
+    // Methods of requests never redirected
+    ignore_methods: Vec<Method>,
+    // Header skipping the redirection, with optional required value
+    bypass_header: Option<(String, Option<String>)>,
+    // User agent substrings of requests never redirected (lowercase)
+    bypass_user_agents: Vec<String>,
+    // Hosts redirected exclusively (empty: all hosts)
+    only_hosts: Vec<HostPattern>,
+    // Answer code for hosts not redirected exclusively (None: pass through)
+    unknown_host_status: Option<StatusCode>,
+    // Hosts never redirected
+    ignore_hosts: Vec<HostPattern>,
+    // Configurations for specific hosts
+    host_policies: Vec<(HostPattern, RedirectScheme)>,
+    // Networks of clients which are never redirected
+    bypass_cidrs: Vec<IpNet>,
+    // Never redirect requests from loopback clients
+    skip_loopback: bool,
+    // Name and optional secret value of a query parameter skipping the redirection
+    bypass_query_param: Option<(String, Option<String>)>,
+    // Name of a cookie skipping the redirection
+    bypass_cookie: Option<String>,
+    // Predicates which must all hold for a request to be redirected
+    predicates: Vec<RedirectPredicate>,
+    // Predicates forcing the redirection of a request despite ignore and bypass rules
+    force_predicates: Vec<RedirectPredicate>,
+    // Paths redirected even though matching ignored paths
+    redirect_paths: Vec<PathPattern>,
+    // Redirect only top-level navigations according to `Sec-Fetch-Dest`
+    navigations_only: bool,
+    // Host of every redirection target instead of the requested one
+    canonical_host: Option<String>,
+    // Canonicalization of the `www.` prefix of the redirection host
+    www_policy: WwwPolicy,
+    // Host names replaced in the redirection target
+    host_rewrites: Vec<(String, String)>,
+    // Drop the query string from the redirection target
+    strip_query: bool,
+    // Query parameters appended to the redirection target
+    query_params: Vec<(String, String)>,
+    // Path prefixes replaced in the redirection target
+    path_rewrites: Vec<(String, String)>,
+    // Trailing slash normalization of the redirection target
+    trailing_slash: Option<TrailingSlash>,
+    // Keep the path of the redirection target as requested, without normalization
+    raw_path: bool,
+    // Lowercase the host of the redirection target
+    lowercase_host: bool,
+    // Status code answered to redirected requests for hosts with uppercase letters
+    mixed_case_host_status: Option<StatusCode>,
+    // Convert Unicode host names of the redirection target to punycode
+    #[cfg(feature = "idna")]
+    punycode_host: bool,
+    // Handling of characters invalid in the path or query of the redirection target
+    location_encoding: LocationEncoding,
+    // String replacements applied to a component of the location
+    scoped_replacements: Vec<(ReplacementScope, String, String)>,
+    // Template of the redirection target
+    location_template: Option<String>,
+    // Closure transforming the built location
+    map_url: Option<UrlMapper>,
+    // Host of the redirection target of requests without host
+    fallback_host: Option<String>,
+    // Status code answered to redirected requests without host
+    missing_host_status: Option<StatusCode>,
+    // Status code answered to redirected requests for hosts with userinfo
+    userinfo_status: Option<StatusCode>,
+    // Drop the port of the requested host when no target port is set
+    drop_port: bool,
+    // Authorities of the redirection target per requested host
+    target_authorities: Vec<(HostPattern, String)>,
+    // Status flags set by a profile, replaced by explicit status settings
+    profile_status: bool,
+    // Networks which can't be parsed, reported when building
+    invalid_networks: Vec<String>,
+    // Status codes which are not redirections, reported when building
+    invalid_status_codes: Vec<u16>,
+    // Headers whose name or value is invalid, reported when building
+    invalid_headers: Vec<String>,
 }
 
 impl RedirectSchemeBuilder {
@@ -18,37 +242,74 @@ impl RedirectSchemeBuilder {
         Self::default()
     }
 
+    /// Create builder from a TOML configuration file
+    ///
+    /// ```toml
+    /// https_to_http = false
+    /// temporary = true
+    /// replacements = [[":8080", ":8443"]]
+    /// ignore_paths = ["/.well-known/acme-challenge/"]
+    /// ```
+    #[cfg(feature = "config")]
+    pub fn from_toml_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let value = fs::read_to_string(path)
+            .map_err(|e| ConfigError::UnreadableConfig(format!("{}: {}", path.display(), e)))?;
+        Self::from_config(&RedirectConfig::from_toml(&value)?)
+    }
+
+    /// Create builder from `REDIRECT_SCHEME_*` environment variables, see
+    /// `RedirectConfig::from_env`
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_config(&RedirectConfig::from_env()?)
+    }
+
+    /// Create builder from a configuration extracted from a Figment
+    #[cfg(feature = "figment-config")]
+    pub fn from_figment(figment: &figment::Figment) -> Result<Self, ConfigError> {
+        Self::from_config(&RedirectConfig::from_figment(figment)?)
+    }
+
+    /// Create builder from a configuration
+    pub fn from_config(config: &RedirectConfig) -> Result<Self, ConfigError> {
+        let mut builder = Self::new();
+        config.apply(&mut builder)?;
+        Ok(builder)
+    }
+
     /// Enabling or disabling of redirections
     pub fn enable(&mut self, value: bool) -> &mut Self {
-        let mut new = self;
+        let new = self;
         new.disable = !value;
         new
     }
 
     /// Set redirection to HTTPS flag
     pub fn http_to_https(&mut self, value: bool) -> &mut Self {
-        let mut new = self;
+        let new = self;
         new.https_to_http = !value;
         new
     }
 
     /// Set redirection to HTTP
     pub fn https_to_http(&mut self) -> &mut Self {
-        let mut new = self;
+        let new = self;
         new.https_to_http = true;
         new
     }
 
     /// Set answer code for permanent redirection
     pub fn permanent(&mut self, value: bool) -> &mut Self {
-        let mut new = self;
+        self.clear_profile_status();
+        let new = self;
         new.temporary = !value;
         new
     }
 
     /// Set answer code for temporary redirection
     pub fn temporary(&mut self) -> &mut Self {
-        let mut new = self;
+        self.clear_profile_status();
+        let new = self;
         new.temporary = true;
         new
     }
@@ -64,13 +325,1271 @@ impl RedirectSchemeBuilder {
         self
     }
 
+    /// Add string replacement applied only to a component of the location, e.g. `:8080` by
+    /// `:8443` in the host without touching a path like `/report:8080`
+    pub fn replace_in<F: ToString, T: ToString>(
+        &mut self,
+        scope: ReplacementScope,
+        from: F,
+        to: T,
+    ) -> &mut Self {
+        self.scoped_replacements
+            .push((scope, from.to_string(), to.to_string()));
+        self
+    }
+
+    /// Set list of proxies whose forwarded scheme headers are trusted
+    pub fn trusted_proxies(&mut self, value: &[IpNet]) -> &mut Self {
+        self.trusted_proxies = value.to_vec();
+        self
+    }
+
+    /// Prefer RFC 7239 `Forwarded` header over legacy `X-Forwarded-*` headers, `true` by default
+    ///
+    /// Set it to `false` for proxies that pass a client supplied `Forwarded` header through
+    /// untouched while setting the `X-Forwarded-*` headers themselves.
+    pub fn prefer_forwarded(&mut self, value: bool) -> &mut Self {
+        self.prefer_x_forwarded = !value;
+        self
+    }
+
+    /// Set name of a custom header carrying the original scheme
+    pub fn scheme_header<S: ToString>(&mut self, value: S) -> &mut Self {
+        self.scheme_header = Some(value.to_string());
+        self
+    }
+
+    /// Read the original scheme from Cloudflare's `CF-Visitor` header
+    pub fn behind_cloudflare(&mut self) -> &mut Self {
+        self.cf_visitor = true;
+        self
+    }
+
+    /// Read the original scheme from AWS CloudFront's `CloudFront-Forwarded-Proto` header
+    pub fn behind_cloudfront(&mut self) -> &mut Self {
+        self.scheme_header("cloudfront-forwarded-proto")
+    }
+
+    /// Set name of a header whose presence marks the request as HTTPS
+    pub fn https_header<S: ToString>(&mut self, value: S) -> &mut Self {
+        self.https_header = Some(value.to_string());
+        self
+    }
+
+    /// Detect TLS termination by Azure App Service's `X-ARR-SSL` header
+    pub fn behind_azure(&mut self) -> &mut Self {
+        self.https_header("x-arr-ssl")
+    }
+
+    /// Detect TLS termination by Fastly's `Fastly-SSL` header
+    pub fn behind_fastly(&mut self) -> &mut Self {
+        self.https_header("fastly-ssl")
+    }
+
+    /// Read the original scheme from Fly.io's `Fly-Forwarded-Proto` header
+    pub fn behind_fly(&mut self) -> &mut Self {
+        self.scheme_header("fly-forwarded-proto")
+    }
+
+    /// Set scheme assumed when neither the request nor its connection reveal it
+    pub fn unknown_scheme_policy(&mut self, value: Assume) -> &mut Self {
+        self.unknown_scheme_policy = value;
+        self
+    }
+
+    /// Determine scheme by the TLS state of the listener only, ignoring forwarded headers
+    pub fn listener_scheme(&mut self, value: bool) -> &mut Self {
+        if value {
+            self.scheme_sources(&[SchemeSource::TlsListener])
+        } else {
+            self.scheme_sources(&[])
+        }
+    }
+
+    /// Set sources of the request scheme in order of priority
+    pub fn scheme_sources(&mut self, value: &[SchemeSource]) -> &mut Self {
+        self.scheme_sources = value.to_vec();
+        self
+    }
+
+    /// Set networks or addresses of proxies whose forwarded scheme headers are trusted, replacing
+    /// the list like `trusted_proxies`
+    ///
+    /// Values which are neither a network in CIDR notation nor an IP address are reported by
+    /// `try_build`.
+    pub fn trust_forwarded_from<S: AsRef<str>>(&mut self, value: &[S]) -> &mut Self {
+        self.trusted_proxies = self.parse_nets(value);
+        self
+    }
+
+    /// Set handling of HTTP/2 cleartext (h2c) requests
+    pub fn h2c_policy(&mut self, value: H2cPolicy) -> &mut Self {
+        self.h2c_policy = value;
+        self
+    }
+
+    /// Set handling of WebSocket handshakes
+    pub fn websocket_policy(&mut self, value: WebSocketPolicy) -> &mut Self {
+        self.websocket_policy = value;
+        self
+    }
+
+    /// Never redirect WebSocket handshakes, which most clients can't follow across schemes
+    pub fn ignore_websocket_upgrades(&mut self, value: bool) -> &mut Self {
+        self.websocket_policy(if value {
+            WebSocketPolicy::PassThrough
+        } else {
+            WebSocketPolicy::Redirect
+        })
+    }
+
+    /// Set interpretation of `X-Forwarded-Proto` headers listing several schemes
+    pub fn forwarded_proto_policy(&mut self, value: ForwardedProtoPolicy) -> &mut Self {
+        self.forwarded_proto_policy = value;
+        self
+    }
+
+    /// Configure for the Kubernetes NGINX ingress controller, trusting `X-Forwarded-Proto` and
+    /// `X-Forwarded-Host` from the cluster network and never redirecting the `kube-probe`
+    /// liveness and readiness probes the kubelet sends straight to the pod over plain HTTP
+    ///
+    /// Trusted proxies, scheme sources and bypassed user agents set before are kept and extended.
+    pub fn preset_nginx_ingress(&mut self) -> &mut Self {
+        self.add_trusted_proxies(PRIVATE_NETWORKS)
+            .add_scheme_sources(&[SchemeSource::XForwardedProto, SchemeSource::ConnectionInfo])
+            .add_bypass_user_agents(&["kube-probe"])
+            .forwarded_host(true)
+    }
+
+    /// Configure for Traefik, trusting `X-Forwarded-Proto` and `X-Forwarded-Host` from private
+    /// networks; Traefik answers ACME challenges and its `/ping` health check itself
+    ///
+    /// Trusted proxies and scheme sources set before are kept and extended.
+    pub fn preset_traefik(&mut self) -> &mut Self {
+        self.add_trusted_proxies(PRIVATE_NETWORKS)
+            .add_scheme_sources(&[SchemeSource::XForwardedProto, SchemeSource::ConnectionInfo])
+            .forwarded_host(true)
+    }
+
+    /// Configure for the Heroku router, which appends its scheme to `X-Forwarded-Proto`
+    ///
+    /// Scheme sources set before are kept and extended. No paths are ignored, so health checks
+    /// reaching the app over plain HTTP need `ignore_paths` or `ignore_health_checks`.
+    pub fn preset_heroku(&mut self) -> &mut Self {
+        self.forwarded_proto_policy(ForwardedProtoPolicy::Last)
+            .add_scheme_sources(&[SchemeSource::XForwardedProto, SchemeSource::ConnectionInfo])
+    }
+
+    /// Prefer the first host of the `X-Forwarded-Host` header of trusted proxies over the `host`
+    /// parameter of the `Forwarded` header when both are present
+    ///
+    /// Either header is used anyway when it's the only one, before the `Host` header;
+    /// `prefer_forwarded(false)` gives `X-Forwarded-Host` precedence as well.
+    pub fn forwarded_host(&mut self, value: bool) -> &mut Self {
+        self.forwarded_host = value;
+        self
+    }
+
+    /// Set synchronous resolver of the request scheme, consulted before any other source
+    pub fn scheme_resolver<F>(&mut self, value: F) -> &mut Self
+    where
+        F: Fn(&ServiceRequest) -> Option<SchemeHint> + Send + Sync + 'static,
+    {
+        self.scheme_resolver = Some(Arc::new(value));
+        self
+    }
+
+    /// Set asynchronous resolver of the request scheme, awaited before deciding on redirection
+    ///
+    /// When the resolver yields `None`, the scheme is determined as usual.
+    pub fn async_scheme_resolver<F, R>(&mut self, value: F) -> &mut Self
+    where
+        F: Fn(&ServiceRequest) -> R + Send + Sync + 'static,
+        R: Future<Output = Option<SchemeHint>> + 'static,
+    {
+        self.async_scheme_resolver = Some(Arc::new(move |req: &ServiceRequest| {
+            value(req).boxed_local()
+        }));
+        self
+    }
+
+    /// Redirect only requests carrying `Upgrade-Insecure-Requests: 1`, i.e. browser navigations
+    pub fn upgrade_insecure_only(&mut self, value: bool) -> &mut Self {
+        self.upgrade_insecure_only = value;
+        self
+    }
+
+    /// Set status code of redirections for requests carrying `Upgrade-Insecure-Requests: 1`
+    pub fn upgrade_insecure_status(&mut self, value: StatusCode) -> &mut Self {
+        self.upgrade_insecure_status = Some(value);
+        self
+    }
+
+    /// Set status code answered to requests without `Upgrade-Insecure-Requests: 1` instead of
+    /// passing them through, when only those requests are redirected
+    pub fn insecure_response(&mut self, value: StatusCode) -> &mut Self {
+        self.insecure_response = Some(value);
+        self
+    }
+
+    /// Set ports of the HTTP and HTTPS listeners, swapped in the host of the redirection target
+    pub fn listen_ports(&mut self, http: u16, https: u16) -> &mut Self {
+        self.http_port(http).https_port(https)
+    }
+
+    /// Set port of the HTTP listener, set in the host of redirections to HTTP
+    pub fn http_port(&mut self, value: u16) -> &mut Self {
+        self.http_port = Some(value);
+        self
+    }
+
+    /// Set port of the HTTPS listener, set in the host of redirections to HTTPS
+    pub fn https_port(&mut self, value: u16) -> &mut Self {
+        self.https_port = Some(value);
+        self
+    }
+
+    /// Set source of the host of the redirection target
+    pub fn host_source(&mut self, value: HostSource) -> &mut Self {
+        self.host_source = value;
+        self
+    }
+
+    /// Set answer code for permanent redirection to "308 Permanent Redirect", which preserves
+    /// the request method
+    pub fn preserve_method(&mut self, value: bool) -> &mut Self {
+        self.clear_profile_status();
+        self.preserve_method = value;
+        self
+    }
+
+    /// Set answer code of redirections, e.g. "302 Found" or "303 See Other"
+    ///
+    /// Status codes which are not a redirection (3xx) are reported by `try_build`.
+    pub fn status_code(&mut self, value: StatusCode) -> &mut Self {
+        if !self.check_status(value) {
+            return self;
+        }
+        self.clear_profile_status();
+        self.status_code = Some(value);
+        self
+    }
+
+    /// Set answer code of redirections for unsafe methods like POST, PUT or PATCH
+    ///
+    /// Status codes which are not a redirection (3xx) are reported by `try_build`.
+    pub fn status_for_unsafe_methods(&mut self, value: StatusCode) -> &mut Self {
+        if self.check_status(value) {
+            self.unsafe_methods_status = Some(value);
+        }
+        self
+    }
+
+    /// Reject requests with the given answer code and plain text body instead of redirecting them
+    pub fn reject<S: ToString>(&mut self, status: StatusCode, body: S) -> &mut Self {
+        self.reject = Some((status, body.to_string()));
+        self
+    }
+
+    /// Answer "426 Upgrade Required" with an `Upgrade: TLS/1.3` header instead of redirecting to
+    /// HTTPS
+    pub fn upgrade_required(&mut self, value: bool) -> &mut Self {
+        self.upgrade_required = value;
+        self
+    }
+
+    /// Set body of redirection responses
+    pub fn redirect_body(&mut self, value: RedirectBody) -> &mut Self {
+        self.redirect_body = value;
+        self
+    }
+
+    /// Set serializer of JSON redirection bodies, `{"location": "..."}` by default
+    pub fn json_serializer<F>(&mut self, value: F) -> &mut Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.json_serializer = Some(Arc::new(value));
+        self
+    }
+
+    /// Add a header to redirection responses
+    ///
+    /// Names or values which are not valid in a header are reported by `try_build`.
+    pub fn redirect_header<N: AsRef<str>, V: AsRef<str>>(
+        &mut self,
+        name: N,
+        value: V,
+    ) -> &mut Self {
+        match (
+            HeaderName::from_bytes(name.as_ref().as_bytes()),
+            HeaderValue::from_str(value.as_ref()),
+        ) {
+            (Ok(name), Ok(value)) => self.redirect_headers.push((name, value)),
+            _ => self
+                .invalid_headers
+                .push(format!("{}: {}", name.as_ref(), value.as_ref())),
+        }
+        self
+    }
+
+    /// Set `Cache-Control` header of redirections, e.g. `max-age=300` to let browsers forget a
+    /// permanent redirection
+    ///
+    /// Without it, permanent redirections ("301 Moved Permanently" and "308 Permanent Redirect")
+    /// are sent with `max-age=86400`, so browsers recheck them daily instead of caching them
+    /// forever.
+    ///
+    /// Values which are not valid in a header are reported by `try_build`.
+    pub fn redirect_cache_control<S: AsRef<str>>(&mut self, value: S) -> &mut Self {
+        match HeaderValue::from_str(value.as_ref()) {
+            Ok(value) => self.cache_control = Some(value),
+            Err(_) => self
+                .invalid_headers
+                .push(format!("Cache-Control: {}", value.as_ref())),
+        }
+        self
+    }
+
+    /// Add `Strict-Transport-Security` header with the given max age to responses of requests
+    /// which already use HTTPS, including those passed through by ignore and bypass rules
+    pub fn hsts(&mut self, max_age: Duration) -> &mut Self {
+        self.hsts_max_age = Some(max_age);
+        self
+    }
+
+    /// Add `includeSubDomains` directive to the `Strict-Transport-Security` header
+    pub fn hsts_include_subdomains(&mut self, value: bool) -> &mut Self {
+        self.hsts_include_subdomains = value;
+        self
+    }
+
+    /// Add `preload` directive to the `Strict-Transport-Security` header
+    pub fn hsts_preload(&mut self, value: bool) -> &mut Self {
+        self.hsts_preload = value;
+        self
+    }
+
+    /// Add `Refresh` header to redirections, for clients ignoring the status code
+    pub fn refresh_header(&mut self, value: bool) -> &mut Self {
+        self.refresh_header = value;
+        self
+    }
+
+    /// Add `<meta http-equiv="refresh">` to HTML bodies of redirections, using an HTML body when
+    /// none is configured
+    pub fn meta_refresh(&mut self, value: bool) -> &mut Self {
+        self.meta_refresh = value;
+        self
+    }
+
+    /// Add `Link: <...>; rel="canonical"` header naming the new location to redirections
+    pub fn canonical_link(&mut self, value: bool) -> &mut Self {
+        self.canonical_link = value;
+        self
+    }
+
+    /// Add `Link: <...>; rel="canonical"` header to responses of requests passed through
+    pub fn canonical_link_pass_through(&mut self, value: bool) -> &mut Self {
+        self.canonical_link_pass_through = value;
+        self
+    }
+
+    /// Set hook inspecting the request and mutating the generated response before it is returned
+    pub fn on_redirect<F>(&mut self, value: F) -> &mut Self
+    where
+        F: Fn(&ServiceRequest, &mut HttpResponse) + Send + Sync + 'static,
+    {
+        self.on_redirect = Some(Arc::new(value));
+        self
+    }
+
+    /// Answer requests whose `Content-Length` exceeds the limit with the given code, e.g.
+    /// "413 Payload Too Large", instead of redirecting them
+    pub fn body_limit(&mut self, limit: u64, status: StatusCode) -> &mut Self {
+        self.body_limit = Some((limit, status));
+        self
+    }
+
+    /// Set producer of redirection responses, replacing the built-in status code, headers and body
+    pub fn responder<R: RedirectResponder + 'static>(&mut self, value: R) -> &mut Self {
+        self.responder = Some(Arc::new(value));
+        self
+    }
+
+    /// Set methods of redirected requests, requests with other methods are passed through
+    pub fn redirect_methods(&mut self, value: &[Method]) -> &mut Self {
+        self.redirect_methods = value.to_vec();
+        self
+    }
+
+    /// Redirect only requests with the methods, e.g. `&[Method::GET, Method::HEAD]`, same as
+    /// `redirect_methods`
+    pub fn redirect_only_methods(&mut self, value: &[Method]) -> &mut Self {
+        self.redirect_methods(value)
+    }
+
+    /// Add `X-Redirected-By` header to generated responses, telling them apart from redirections
+    /// by proxies
+    pub fn diagnostic_header(&mut self, value: bool) -> &mut Self {
+        self.diagnostic_header = value;
+        self
+    }
+
+    /// Set answer code of redirections for paths under the prefix, the longest matching prefix
+    /// wins
+    ///
+    /// The prefix matches whole segments: `/api` matches `/api` and `/api/users`, not `/apiary`.
+    /// Status codes which are not a redirection (3xx) are reported by `try_build`.
+    pub fn path_status<S: ToString>(&mut self, prefix: S, status: StatusCode) -> &mut Self {
+        if self.check_status(status) {
+            self.path_statuses.push((prefix.to_string(), status));
+        }
+        self
+    }
+
+    /// Add prefix of paths which are never redirected
+    pub fn ignore_path<S: ToString>(&mut self, value: S) -> &mut Self {
+        self.ignore_paths
+            .push(PathPattern::Prefix(value.to_string()));
+        self
+    }
+
+    /// Add prefixes of paths which are never redirected
+    pub fn ignore_paths<I, S>(&mut self, value: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: ToString,
+    {
+        self.ignore_paths.extend(
+            value
+                .into_iter()
+                .map(|prefix| PathPattern::Prefix(prefix.to_string())),
+        );
+        self
+    }
+
+    /// Add glob pattern of paths which are never redirected, e.g. `/static/**/*.css`
+    pub fn ignore_path_glob<S: AsRef<str>>(&mut self, value: S) -> &mut Self {
+        self.ignore_paths
+            .push(PathPattern::Glob(GlobPattern::new(value.as_ref())));
+        self
+    }
+
+    /// Add actix route of paths which are never redirected, e.g. `/users/{id}/avatar` or
+    /// `/files/{tail:.*}`
+    pub fn ignore_path_route<S: ToString>(&mut self, value: S) -> &mut Self {
+        self.ignore_path_routes.push(value.to_string());
+        self
+    }
+
+    /// Add regular expression of paths which are never redirected, e.g. `^/(health|metrics)$`
+    #[cfg(feature = "regex")]
+    pub fn ignore_path_regex<S: ToString>(&mut self, value: S) -> &mut Self {
+        self.ignore_path_regexes.push(value.to_string());
+        self
+    }
+
+    /// Add path which is never redirected, matched exactly
+    pub fn ignore_path_exact<S: ToString>(&mut self, value: S) -> &mut Self {
+        self.ignore_paths
+            .push(PathPattern::Exact(value.to_string()));
+        self
+    }
+
+    /// Add pattern of paths which are never redirected
+    pub fn ignore_path_pattern(&mut self, value: PathPattern) -> &mut Self {
+        self.ignore_paths.push(value);
+        self
+    }
+
+    /// Add prefix of paths which are redirected even though matching ignored paths, e.g.
+    /// `/api/login` under the ignored `/api/`
+    pub fn redirect_path<S: ToString>(&mut self, value: S) -> &mut Self {
+        self.redirect_paths
+            .push(PathPattern::Prefix(value.to_string()));
+        self
+    }
+
+    /// Add path which is redirected even though matching ignored paths, matched exactly
+    pub fn redirect_path_exact<S: ToString>(&mut self, value: S) -> &mut Self {
+        self.redirect_paths
+            .push(PathPattern::Exact(value.to_string()));
+        self
+    }
+
+    /// Add pattern of paths which are redirected even though matching ignored paths
+    pub fn redirect_path_pattern(&mut self, value: PathPattern) -> &mut Self {
+        self.redirect_paths.push(value);
+        self
+    }
+
+    /// Add prefix of paths which are redirected exclusively, other paths are passed through
+    pub fn only_path<S: ToString>(&mut self, value: S) -> &mut Self {
+        self.only_paths.push(PathPattern::Prefix(value.to_string()));
+        self
+    }
+
+    /// Set file extensions of paths which are never redirected, e.g. `["css", "js"]`
+    pub fn ignore_extensions<S: AsRef<str>>(&mut self, value: &[S]) -> &mut Self {
+        self.ignore_extensions = value
+            .iter()
+            .map(|ext| ext.as_ref().trim_start_matches('.').to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Set methods of requests which are never redirected
+    pub fn ignore_methods(&mut self, value: &[Method]) -> &mut Self {
+        self.ignore_methods = value.to_vec();
+        self
+    }
+
+    /// Set name of a header skipping the redirection of a request
+    pub fn bypass_header<S: ToString>(&mut self, name: S) -> &mut Self {
+        self.bypass_header = Some((name.to_string(), None));
+        self
+    }
+
+    /// Set name and secret value of a header skipping the redirection of a request
+    pub fn bypass_header_secret<N: ToString, V: ToString>(
+        &mut self,
+        name: N,
+        value: V,
+    ) -> &mut Self {
+        self.bypass_header = Some((name.to_string(), Some(value.to_string())));
+        self
+    }
+
+    /// Set user agent substrings of requests which are never redirected, e.g. `kube-probe`
+    pub fn bypass_user_agents<S: AsRef<str>>(&mut self, value: &[S]) -> &mut Self {
+        self.bypass_user_agents = value
+            .iter()
+            .map(|agent| agent.as_ref().to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Never redirect common health check paths like `/healthz`, `/livez`, `/readyz`, `/ping`
+    /// and `/status`
+    pub fn ignore_health_checks(&mut self) -> &mut Self {
+        for path in HEALTH_CHECK_PATHS {
+            self.ignore_path_exact(path);
+        }
+        self
+    }
+
+    /// Never redirect ACME HTTP-01 challenges under `/.well-known/acme-challenge/`
+    pub fn allow_acme_challenge(&mut self) -> &mut Self {
+        self.ignore_path("/.well-known/acme-challenge/")
+    }
+
+    /// Never redirect certificate authority validations under `/.well-known/pki-validation/`
+    pub fn allow_pki_validation(&mut self) -> &mut Self {
+        self.ignore_path("/.well-known/pki-validation/")
+    }
+
+    /// Set hosts which are redirected exclusively, requests for other hosts are passed through
+    ///
+    /// Hosts like `*.example.com` match all subdomains.
+    pub fn only_hosts<S: AsRef<str>>(&mut self, value: &[S]) -> &mut Self {
+        self.only_hosts = value
+            .iter()
+            .map(|host| HostPattern::new(host.as_ref()))
+            .collect();
+        self
+    }
+
+    /// Set answer code for requests for hosts not listed by `only_hosts` instead of passing them
+    /// through
+    pub fn unknown_host_status(&mut self, value: StatusCode) -> &mut Self {
+        self.unknown_host_status = Some(value);
+        self
+    }
+
+    /// Set hosts which are never redirected
+    ///
+    /// Hosts like `*.example.com` match all subdomains.
+    pub fn ignore_hosts<S: AsRef<str>>(&mut self, value: &[S]) -> &mut Self {
+        self.ignore_hosts = value
+            .iter()
+            .map(|host| HostPattern::new(host.as_ref()))
+            .collect();
+        self
+    }
+
+    /// Set configuration for requests for the host, instead of this configuration
+    ///
+    /// Hosts like `*.example.com` match all subdomains, the first matching host wins.
+    pub fn host_policy<S: AsRef<str>>(&mut self, host: S, policy: RedirectScheme) -> &mut Self {
+        self.host_policies
+            .push((HostPattern::new(host.as_ref()), policy));
+        self
+    }
+
+    /// Set networks of clients which are never redirected, e.g. `10.0.0.0/8`
+    ///
+    /// The client address is taken from `X-Forwarded-For` when the peer is a trusted proxy.
+    /// Values which are neither a network in CIDR notation nor an IP address are reported by
+    /// `try_build`.
+    pub fn bypass_cidrs<S: AsRef<str>>(&mut self, value: &[S]) -> &mut Self {
+        self.bypass_cidrs = self.parse_nets(value);
+        self
+    }
+
+    /// Never redirect requests from loopback clients like `127.0.0.1` and `::1`
+    ///
+    /// Configure the trusted proxies when a local reverse proxy forwards all requests.
+    pub fn skip_loopback(&mut self, value: bool) -> &mut Self {
+        self.skip_loopback = value;
+        self
+    }
+
+    /// Set name of a query parameter skipping the redirection of a request
+    pub fn bypass_query_flag<S: ToString>(&mut self, name: S) -> &mut Self {
+        self.bypass_query_param = Some((name.to_string(), None));
+        self
+    }
+
+    /// Set name and secret value of a query parameter skipping the redirection of a request
+    ///
+    /// The value is compared to the raw, not percent-decoded, query value.
+    pub fn bypass_query_param<N: ToString, V: ToString>(&mut self, name: N, value: V) -> &mut Self {
+        self.bypass_query_param = Some((name.to_string(), Some(value.to_string())));
+        self
+    }
+
+    /// Set name of a cookie skipping the redirection of a request
+    pub fn bypass_cookie<S: ToString>(&mut self, name: S) -> &mut Self {
+        self.bypass_cookie = Some(name.to_string());
+        self
+    }
+
+    /// Add predicate which must hold for a request to be redirected, in addition to the
+    /// built-in checks
+    ///
+    /// Requests matching a `force_when` predicate are redirected even if it doesn't hold.
+    pub fn when<F>(&mut self, value: F) -> &mut Self
+    where
+        F: Fn(&ServiceRequest) -> bool + Send + Sync + 'static,
+    {
+        self.predicates.push(Arc::new(value));
+        self
+    }
+
+    /// Add predicate forcing the redirection of a request, overriding every ignore and bypass
+    /// rule as well as guards and `when` predicates
+    ///
+    /// Requests already using the target scheme are still passed through, and `enable(false)`
+    /// still turns the redirection off.
+    pub fn force_when<F>(&mut self, value: F) -> &mut Self
+    where
+        F: Fn(&ServiceRequest) -> bool + Send + Sync + 'static,
+    {
+        self.force_predicates.push(Arc::new(value));
+        self
+    }
+
+    /// Never redirect requests matching the guard made by the factory, e.g.
+    /// `|| guard::Header("x-internal", "1")`
+    ///
+    /// The guard is made for every request, as guards like `guard::Not` and `guard::Any` can't
+    /// be shared between threads.
+    pub fn guard<F, G>(&mut self, factory: F) -> &mut Self
+    where
+        F: Fn() -> G + Send + Sync + 'static,
+        G: Guard + 'static,
+    {
+        self.when(move |req| !factory().check(req.head()))
+    }
+
+    /// Redirect only top-level navigations, requests whose `Sec-Fetch-Dest` header is not
+    /// `document` are passed through
+    ///
+    /// Requests without the header, e.g. from older browsers or non-browser clients, are redirected.
+    pub fn navigations_only(&mut self, value: bool) -> &mut Self {
+        self.navigations_only = value;
+        self
+    }
+
+    /// Set host of every redirection target regardless of the requested host, e.g.
+    /// `www.example.com`
+    pub fn canonical_host<S: ToString>(&mut self, value: S) -> &mut Self {
+        self.canonical_host = Some(value.to_string());
+        self
+    }
+
+    /// Add the `www.` prefix to the host of the redirection target, e.g. `example.com` to
+    /// `www.example.com`
+    pub fn add_www(&mut self) -> &mut Self {
+        self.www_policy = WwwPolicy::Add;
+        self
+    }
+
+    /// Remove the `www.` prefix from the host of the redirection target, e.g. `www.example.com`
+    /// to `example.com`
+    pub fn remove_www(&mut self) -> &mut Self {
+        self.www_policy = WwwPolicy::Remove;
+        self
+    }
+
+    /// Add host name replaced in the redirection target, e.g. `old.example.com` by
+    /// `new.example.com`
+    pub fn rewrite_host<F: ToString, T: ToString>(&mut self, from: F, to: T) -> &mut Self {
+        self.host_rewrites.push((from.to_string(), to.to_string()));
+        self
+    }
+
+    /// Drop the query string from the redirection target, so its parameters are not echoed back
+    pub fn strip_query(&mut self, value: bool) -> &mut Self {
+        self.strip_query = value;
+        self
+    }
+
+    /// Add query parameter appended to the redirection target, e.g. `from=http`
+    pub fn append_query<N: ToString, V: ToString>(&mut self, name: N, value: V) -> &mut Self {
+        self.query_params
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Add path prefix replaced in the redirection target, e.g. `/old-app` by `/app`
+    ///
+    /// The prefix matches whole segments only, so `/old-app` doesn't match `/old-apps`.
+    pub fn rewrite_path_prefix<F: ToString, T: ToString>(&mut self, from: F, to: T) -> &mut Self {
+        self.path_rewrites.push((from.to_string(), to.to_string()));
+        self
+    }
+
+    /// Normalize the trailing slash of the redirection target like the `NormalizePath`
+    /// middleware, avoiding a second redirection
+    pub fn trailing_slash(&mut self, value: TrailingSlash) -> &mut Self {
+        self.trailing_slash = Some(value);
+        self
+    }
+
+    /// Keep the path of the redirection target as requested, by default duplicate slashes and
+    /// `.` and `..` segments are removed
+    pub fn raw_path(&mut self, value: bool) -> &mut Self {
+        self.raw_path = value;
+        self
+    }
+
+    /// Lowercase the host of the redirection target, so caches and analytics see a single form
+    pub fn lowercase_host(&mut self, value: bool) -> &mut Self {
+        self.lowercase_host = value;
+        self
+    }
+
+    /// Set status code answered instead of a redirection to requests for hosts with uppercase
+    /// letters, e.g. `StatusCode::BAD_REQUEST`
+    pub fn mixed_case_host_status(&mut self, value: StatusCode) -> &mut Self {
+        self.mixed_case_host_status = Some(value);
+        self
+    }
+
+    /// Convert Unicode host names of the redirection target to punycode, e.g. `bücher.example` to
+    /// `xn--bcher-kva.example`
+    ///
+    /// Without the conversion, requests for Unicode host names are answered with
+    /// `400 Bad Request`, as they can't be part of a valid location.
+    #[cfg(feature = "idna")]
+    pub fn punycode_host(&mut self, value: bool) -> &mut Self {
+        self.punycode_host = value;
+        self
+    }
+
+    /// Set handling of characters invalid in the path or query of the redirection target, e.g.
+    /// spaces introduced by path rewrites
+    pub fn location_encoding(&mut self, value: LocationEncoding) -> &mut Self {
+        self.location_encoding = value;
+        self
+    }
+
+    /// Set template of the redirection target, e.g. `{scheme}://{host}:{https_port}{path}{query}`
+    ///
+    /// The placeholders are `{scheme}`, `{host}` without port, `{port}` of the target host,
+    /// `{http_port}` and `{https_port}` of the listeners, `{path}` and `{query}` including its
+    /// leading `?`. Requests whose filled template is not a valid URI are answered with
+    /// `400 Bad Request`.
+    pub fn location_template<S: ToString>(&mut self, value: S) -> &mut Self {
+        self.location_template = Some(value.to_string());
+        self
+    }
+
+    /// Set closure transforming the built location, e.g. to add signed parameters
+    pub fn map_url<F>(&mut self, value: F) -> &mut Self
+    where
+        F: Fn(String, &ServiceRequest) -> String + Send + Sync + 'static,
+    {
+        self.map_url = Some(Arc::new(value));
+        self
+    }
+
+    /// Set host of the redirection target of requests without `Host` header, as sent by
+    /// HTTP/1.0 clients, instead of the configured host of the server
+    pub fn fallback_host<S: ToString>(&mut self, value: S) -> &mut Self {
+        self.fallback_host = Some(value.to_string());
+        self
+    }
+
+    /// Set status code answered instead of a redirection to requests without `Host` header, e.g.
+    /// `StatusCode::BAD_REQUEST`
+    pub fn missing_host_status(&mut self, value: StatusCode) -> &mut Self {
+        self.missing_host_status = Some(value);
+        self
+    }
+
+    /// Set status code answered instead of a redirection to requests for hosts with userinfo like
+    /// `user:pass@example.com`, e.g. `StatusCode::BAD_REQUEST`
+    ///
+    /// By default the userinfo is stripped from the host of the redirection target.
+    pub fn userinfo_status(&mut self, value: StatusCode) -> &mut Self {
+        self.userinfo_status = Some(value);
+        self
+    }
+
+    /// Set whether the port of the requested host is kept in the redirection target, `true` by
+    /// default
+    ///
+    /// Ports set with `http_port` and `https_port` replace the requested port either way, and
+    /// are omitted when they are the default port of the target scheme. Once one of them is
+    /// set, the target scheme uses its default port unless its own port is set too.
+    pub fn preserve_port(&mut self, value: bool) -> &mut Self {
+        self.drop_port = !value;
+        self
+    }
+
+    /// Redirect requests for the host to a different authority, e.g. `legacy.example.com` to
+    /// `app.example.com` or `app.example.com:8443`
+    ///
+    /// Hosts like `*.example.com` match all subdomains. The authority is used as given, without
+    /// port mapping or other host canonicalization.
+    pub fn redirect_host<H: AsRef<str>, A: ToString>(
+        &mut self,
+        host: H,
+        authority: A,
+    ) -> &mut Self {
+        self.target_authorities
+            .push((HostPattern::new(host.as_ref()), authority.to_string()));
+        self
+    }
+
+    /// Apply the settings of a deployment profile, which can then be tweaked
+    ///
+    /// HSTS is only set when redirecting from HTTP to HTTPS, so choose the direction first. The
+    /// status set by the profile is replaced as a whole by later calls to `permanent`,
+    /// `temporary`, `preserve_method` or `status_code`.
+    pub fn profile(&mut self, value: Profile) -> &mut Self {
+        self.profile_status = true;
+        match value {
+            Profile::Dev => {
+                self.temporary = true;
+                self.preserve_method = false;
+                self.http_port(8080).https_port(8443).skip_loopback(true)
+            }
+            Profile::Staging => {
+                self.temporary = true;
+                self.preserve_method = false;
+                if !self.https_to_http {
+                    self.hsts(Duration::from_secs(300));
+                }
+                self
+            }
+            Profile::Prod => {
+                self.temporary = false;
+                self.preserve_method = true;
+                if !self.https_to_http {
+                    self.hsts(HSTS_PRELOAD_MIN_MAX_AGE);
+                }
+                self.lowercase_host(true)
+                    .missing_host_status(StatusCode::BAD_REQUEST)
+                    .userinfo_status(StatusCode::BAD_REQUEST)
+                    .unknown_host_status(StatusCode::MISDIRECTED_REQUEST)
+            }
+        }
+    }
+
+    // Parse networks or addresses, keeping invalid values for `try_build` to report
+    fn parse_nets<S: AsRef<str>>(&mut self, values: &[S]) -> Vec<IpNet> {
+        let mut nets = Vec::with_capacity(values.len());
+        for value in values {
+            match parse_net(value.as_ref()) {
+                Some(net) => nets.push(net),
+                None => self.invalid_networks.push(value.as_ref().to_owned()),
+            }
+        }
+        nets
+    }
+
+    // Trust the given networks in addition to the proxies set before
+    fn add_trusted_proxies<S: AsRef<str>>(&mut self, values: &[S]) -> &mut Self {
+        for net in self.parse_nets(values) {
+            if !self.trusted_proxies.contains(&net) {
+                self.trusted_proxies.push(net);
+            }
+        }
+        self
+    }
+
+    // Append the scheme sources which aren't consulted yet
+    fn add_scheme_sources(&mut self, values: &[SchemeSource]) -> &mut Self {
+        for source in values {
+            if !self.scheme_sources.contains(source) {
+                self.scheme_sources.push(*source);
+            }
+        }
+        self
+    }
+
+    // Append the user agent substrings which aren't bypassed yet
+    fn add_bypass_user_agents(&mut self, values: &[&str]) -> &mut Self {
+        for agent in values {
+            let agent = agent.to_lowercase();
+            if !self.bypass_user_agents.contains(&agent) {
+                self.bypass_user_agents.push(agent);
+            }
+        }
+        self
+    }
+
+    // Whether the status code is a redirection, keeping others for `try_build` to report
+    fn check_status(&mut self, status: StatusCode) -> bool {
+        if !status.is_redirection() {
+            self.invalid_status_codes.push(status.as_u16());
+        }
+        status.is_redirection()
+    }
+
+    // Drop the status flags set by a profile before an explicit status setting
+    fn clear_profile_status(&mut self) {
+        if self.profile_status {
+            self.profile_status = false;
+            self.temporary = false;
+            self.preserve_method = false;
+        }
+    }
+
+    /// Build RedirectScheme, checking the configuration for errors
+    pub fn try_build(&self) -> Result<RedirectScheme, ConfigError> {
+        if self.hsts_preload
+            && (!self.hsts_include_subdomains
+                || !matches!(self.hsts_max_age, Some(max_age) if max_age >= HSTS_PRELOAD_MIN_MAX_AGE))
+        {
+            return Err(ConfigError::InvalidHstsPreload);
+        }
+        #[cfg(feature = "regex")]
+        RegexSet::new(&self.ignore_path_regexes)
+            .map_err(|e| ConfigError::InvalidIgnorePattern(e.to_string()))?;
+        for route in &self.ignore_path_routes {
+            check_route(route)?;
+        }
+        for pattern in self.ignore_paths.iter().chain(self.redirect_paths.iter()) {
+            if let PathPattern::Exact(path) | PathPattern::Prefix(path) = pattern {
+                if !path.starts_with('/') {
+                    return Err(ConfigError::InvalidIgnorePattern(format!(
+                        "{:?} doesn't start with /",
+                        path
+                    )));
+                }
+            }
+        }
+        if let Some(value) = self.invalid_networks.first() {
+            return Err(ConfigError::InvalidNetwork(value.clone()));
+        }
+        if let Some(code) = self.invalid_status_codes.first() {
+            return Err(ConfigError::InvalidStatusCode(*code));
+        }
+        if let Some(header) = self.invalid_headers.first() {
+            return Err(ConfigError::InvalidHeader(header.clone()));
+        }
+        for port in self.http_port.iter().chain(self.https_port.iter()) {
+            if *port == 0 || self.http_port == self.https_port {
+                return Err(ConfigError::InvalidPort(*port));
+            }
+        }
+        if self.https_to_http {
+            if self.hsts_max_age.is_some() {
+                return Err(ConfigError::ConflictingDirection("hsts"));
+            }
+            if self.upgrade_insecure_only {
+                return Err(ConfigError::ConflictingDirection("upgrade_insecure_only"));
+            }
+            if self.upgrade_required {
+                return Err(ConfigError::ConflictingDirection("upgrade_required"));
+            }
+        }
+        let conflicts = [
+            (
+                "canonical_host",
+                self.canonical_host.is_some(),
+                "host_policy",
+                !self.host_policies.is_empty(),
+            ),
+            (
+                "canonical_host",
+                self.canonical_host.is_some(),
+                "redirect_host",
+                !self.target_authorities.is_empty(),
+            ),
+            (
+                "temporary",
+                self.temporary && !self.profile_status,
+                "status_code",
+                self.status_code.is_some(),
+            ),
+            (
+                "temporary",
+                self.temporary && !self.profile_status,
+                "preserve_method",
+                self.preserve_method,
+            ),
+            (
+                "temporary",
+                self.temporary && !self.profile_status,
+                "status_for_unsafe_methods",
+                self.unsafe_methods_status.is_some(),
+            ),
+            (
+                "reject",
+                self.reject.is_some(),
+                "upgrade_required",
+                self.upgrade_required,
+            ),
+        ];
+        if let Some((first, _, second, _)) = conflicts
+            .iter()
+            .find(|(_, first_set, _, second_set)| *first_set && *second_set)
+        {
+            return Err(ConfigError::ConflictingSettings(first, second));
+        }
+        for scope in &[
+            ReplacementScope::Whole,
+            ReplacementScope::Host,
+            ReplacementScope::Path,
+            ReplacementScope::Query,
+        ] {
+            let unscoped: &[(String, String)] = if *scope == ReplacementScope::Whole {
+                &self.replacements[..]
+            } else {
+                &[]
+            };
+            let rules: Vec<(&str, &str)> = unscoped
+                .iter()
+                .map(|(from, to)| (from.as_str(), to.as_str()))
+                .chain(
+                    self.scoped_replacements
+                        .iter()
+                        .filter(|(rule_scope, _, _)| rule_scope == scope)
+                        .map(|(_, from, to)| (from.as_str(), to.as_str())),
+                )
+                .collect();
+            check_replacements(&rules)?;
+        }
+        Ok(self.scheme())
+    }
+
     /// Build RedirectScheme
+    ///
+    /// # Panics
+    ///
+    /// Panics on every error `try_build` reports, e.g. conflicting settings, a status code which
+    /// is not a redirection or an ignore path pattern which can't be compiled. Call `try_build`
+    /// instead to handle the `ConfigError`.
     pub fn build(&self) -> RedirectScheme {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // RedirectScheme of a configuration checked by `try_build`
+    fn scheme(&self) -> RedirectScheme {
         RedirectScheme {
             disable: self.disable,
             https_to_http: self.https_to_http,
             temporary: self.temporary,
             replacements: self.replacements.clone(),
+            trusted_proxies: self.trusted_proxies.clone(),
+            prefer_x_forwarded: self.prefer_x_forwarded,
+            scheme_header: self.scheme_header.clone(),
+            cf_visitor: self.cf_visitor,
+            https_header: self.https_header.clone(),
+            unknown_scheme_policy: self.unknown_scheme_policy,
+            scheme_sources: self.scheme_sources.clone(),
+            h2c_policy: self.h2c_policy,
+            websocket_policy: self.websocket_policy,
+            forwarded_proto_policy: self.forwarded_proto_policy,
+            forwarded_host: self.forwarded_host,
+            scheme_resolver: self.scheme_resolver.clone(),
+            async_scheme_resolver: self.async_scheme_resolver.clone(),
+            upgrade_insecure_only: self.upgrade_insecure_only,
+            upgrade_insecure_status: self.upgrade_insecure_status,
+            insecure_response: self.insecure_response,
+            http_port: self.http_port,
+            https_port: self.https_port,
+            host_source: self.host_source,
+            preserve_method: self.preserve_method,
+            status_code: self.status_code,
+            unsafe_methods_status: self.unsafe_methods_status,
+            reject: self.reject.clone(),
+            upgrade_required: self.upgrade_required,
+            redirect_body: self.redirect_body,
+            json_serializer: self.json_serializer.clone(),
+            redirect_headers: self.redirect_headers.clone(),
+            cache_control: self.cache_control.clone(),
+            hsts_max_age: self.hsts_max_age,
+            hsts_include_subdomains: self.hsts_include_subdomains,
+            hsts_preload: self.hsts_preload,
+            refresh_header: self.refresh_header,
+            meta_refresh: self.meta_refresh,
+            canonical_link: self.canonical_link,
+            canonical_link_pass_through: self.canonical_link_pass_through,
+            on_redirect: self.on_redirect.clone(),
+            body_limit: self.body_limit,
+            responder: self.responder.clone(),
+            redirect_methods: self.redirect_methods.clone(),
+            diagnostic_header: self.diagnostic_header,
+            path_statuses: self.path_statuses.clone(),
+            ignore_paths: self
+                .ignore_paths
+                .iter()
+                .cloned()
+                .chain(
+                    self.ignore_path_routes
+                        .iter()
+                        .map(|route| PathPattern::Route(ResourceDef::new(route.as_str()))),
+                )
+                .collect(),
+            #[cfg(feature = "regex")]
+            ignore_path_regex: if self.ignore_path_regexes.is_empty() {
+                None
+            } else {
+                Some(
+                    RegexSet::new(&self.ignore_path_regexes)
+                        .unwrap_or_else(|e| panic!("invalid ignore path pattern: {}", e)),
+                )
+            },
+            only_paths: self.only_paths.clone(),
+            ignore_extensions: self.ignore_extensions.clone(),
+            ignore_methods: self.ignore_methods.clone(),
+            bypass_header: self.bypass_header.clone(),
+            bypass_user_agents: self.bypass_user_agents.clone(),
+            only_hosts: self.only_hosts.clone(),
+            unknown_host_status: self.unknown_host_status,
+            ignore_hosts: self.ignore_hosts.clone(),
+            host_policies: self.host_policies.clone(),
+            bypass_cidrs: self.bypass_cidrs.clone(),
+            skip_loopback: self.skip_loopback,
+            bypass_query_param: self.bypass_query_param.clone(),
+            bypass_cookie: self.bypass_cookie.clone(),
+            predicates: self.predicates.clone(),
+            force_predicates: self.force_predicates.clone(),
+            redirect_paths: self.redirect_paths.clone(),
+            navigations_only: self.navigations_only,
+            canonical_host: self.canonical_host.clone(),
+            www_policy: self.www_policy,
+            host_rewrites: self.host_rewrites.clone(),
+            strip_query: self.strip_query,
+            query_params: self.query_params.clone(),
+            path_rewrites: self.path_rewrites.clone(),
+            trailing_slash: self.trailing_slash,
+            raw_path: self.raw_path,
+            lowercase_host: self.lowercase_host,
+            mixed_case_host_status: self.mixed_case_host_status,
+            #[cfg(feature = "idna")]
+            punycode_host: self.punycode_host,
+            location_encoding: self.location_encoding,
+            scoped_replacements: self.scoped_replacements.clone(),
+            location_template: self.location_template.clone(),
+            map_url: self.map_url.clone(),
+            fallback_host: self.fallback_host.clone(),
+            missing_host_status: self.missing_host_status,
+            userinfo_status: self.userinfo_status,
+            drop_port: self.drop_port,
+            target_authorities: self.target_authorities.clone(),
         }
     }
 }
+
+// Network in CIDR notation or single IP address
+fn parse_net(value: &str) -> Option<IpNet> {
+    value
+        .parse()
+        .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+        .ok()
+}
+
+// Replacements applied in order to the same text, rejecting empty patterns, outputs invalid in
+// a location and rules rewriting the output of or shadowed by an earlier rule
+fn check_replacements(rules: &[(&str, &str)]) -> Result<(), ConfigError> {
+    for (i, (from, to)) in rules.iter().enumerate() {
+        if from.is_empty() {
+            return Err(ConfigError::InvalidReplacement(format!(
+                "empty pattern replaced by {:?}",
+                to
+            )));
+        }
+        if !to.bytes().all(|byte| byte.is_ascii_graphic()) {
+            return Err(ConfigError::InvalidReplacement(format!(
+                "{:?} can't appear in a location",
+                to
+            )));
+        }
+        if let Some((earlier, _)) = rules[..i]
+            .iter()
+            .find(|(earlier_from, earlier_to)| earlier_to.contains(from) || earlier_from == from)
+        {
+            return Err(ConfigError::InvalidReplacement(format!(
+                "{:?} conflicts with the earlier replacement of {:?}",
+                from, earlier
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Check the syntax of an actix route, whose compilation panics on malformed patterns like
+// unbalanced braces
+fn check_route(route: &str) -> Result<(), ConfigError> {
+    let invalid =
+        |reason: String| ConfigError::InvalidIgnorePattern(format!("{:?}: {}", route, reason));
+    let mut names = Vec::new();
+    let mut rest = route;
+    while let Some(start) = rest.find('{') {
+        let mut depth = 0;
+        let end = rest[start..]
+            .find(|c| {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => return false,
+                }
+                depth == 0
+            })
+            .ok_or_else(|| invalid("unbalanced braces".to_owned()))?;
+        let segment = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+        let (name, pattern) = match segment.find(':') {
+            Some(_) if rest == "*" => {
+                return Err(invalid(format!(
+                    "tail segment {{{}}} can't have a regular expression",
+                    segment
+                )))
+            }
+            Some(i) => (&segment[..i], &segment[i + 1..]),
+            None => (segment, "[^/]+"),
+        };
+        if names.contains(&name) {
+            return Err(invalid(format!("duplicate segment name {:?}", name)));
+        }
+        regex_syntax::Parser::new()
+            .parse(&format!("(?P<{}>{})", name, pattern))
+            .map_err(|e| invalid(format!("segment {{{}}}: {}", segment, e)))?;
+        names.push(name);
+    }
+    if names.len() > MAX_ROUTE_SEGMENTS {
+        return Err(invalid(format!(
+            "more than {} dynamic segments",
+            MAX_ROUTE_SEGMENTS
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use crate::builder::RedirectSchemeBuilder;
+use clap::Args;
+
+/// Command line arguments configuring the middleware, to be flattened into the arguments of a
+/// binary.
+///
+/// ```rust,ignore
+/// #[derive(clap::Parser)]
+/// struct Cli {
+///     #[clap(flatten)]
+///     redirect: RedirectSchemeArgs,
+/// }
+///
+/// let cli = Cli::parse();
+/// let redirect = RedirectSchemeBuilder::from(&cli.redirect).build();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Args)]
+pub struct RedirectSchemeArgs {
+    /// Redirect HTTP requests to HTTPS
+    #[clap(long = "redirect-https")]
+    pub redirect_https: bool,
+    /// Redirect HTTPS requests to HTTP
+    #[clap(long = "redirect-http", conflicts_with = "redirect-https")]
+    pub redirect_http: bool,
+    /// Redirect temporarily instead of permanently
+    #[clap(long = "redirect-temporary")]
+    pub redirect_temporary: bool,
+    /// Port of the HTTP listener, set in redirections to HTTP
+    #[clap(long = "redirect-http-port", value_name = "PORT")]
+    pub redirect_http_port: Option<u16>,
+    /// Port of the HTTPS listener, set in redirections to HTTPS
+    #[clap(long = "redirect-https-port", value_name = "PORT")]
+    pub redirect_https_port: Option<u16>,
+    /// Prefix of paths which are never redirected, can be repeated
+    #[clap(long = "redirect-ignore-path", value_name = "PREFIX")]
+    pub redirect_ignore_paths: Vec<String>,
+}
+
+impl From<&RedirectSchemeArgs> for RedirectSchemeBuilder {
+    fn from(args: &RedirectSchemeArgs) -> Self {
+        let mut builder = RedirectSchemeBuilder::new();
+        builder
+            .enable(args.redirect_https || args.redirect_http)
+            .http_to_https(!args.redirect_http)
+            .permanent(!args.redirect_temporary)
+            .ignore_paths(&args.redirect_ignore_paths);
+        if let Some(port) = args.redirect_http_port {
+            builder.http_port(port);
+        }
+        if let Some(port) = args.redirect_https_port {
+            builder.https_port(port);
+        }
+        builder
+    }
+}
+
+impl From<RedirectSchemeArgs> for RedirectSchemeBuilder {
+    fn from(args: RedirectSchemeArgs) -> Self {
+        RedirectSchemeBuilder::from(&args)
+    }
+}
@@ -0,0 +1,164 @@
+use crate::builder::RedirectSchemeBuilder;
+use crate::error::ConfigError;
+use actix_web::http::StatusCode;
+#[cfg(feature = "figment-config")]
+use figment::{
+    value::{Dict, Map},
+    Figment, Metadata, Profile, Provider,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::env;
+
+/// Configuration of the middleware loaded at runtime, e.g. from a TOML file.
+///
+/// Unset values keep the defaults of the builder.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct RedirectConfig {
+    /// Whether redirections are enabled
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub enable: Option<bool>,
+    /// Whether HTTPS requests are redirected to HTTP instead of the other way round
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub https_to_http: Option<bool>,
+    /// Whether redirections are temporary
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub temporary: Option<bool>,
+    /// Status code of redirections, e.g. `308`
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub status_code: Option<u16>,
+    /// String replacements applied to the location, e.g. `[[":8080", ":8443"]]`
+    pub replacements: Vec<(String, String)>,
+    /// Prefixes of paths which are never redirected
+    pub ignore_paths: Vec<String>,
+}
+
+impl RedirectConfig {
+    /// Parse the configuration from a TOML document
+    #[cfg(feature = "config")]
+    pub fn from_toml(value: &str) -> Result<Self, ConfigError> {
+        toml::from_str(value).map_err(|e| ConfigError::InvalidConfig(e.to_string()))
+    }
+
+    /// Read the configuration from `REDIRECT_SCHEME_ENABLE`, `REDIRECT_SCHEME_HTTPS_TO_HTTP`,
+    /// `REDIRECT_SCHEME_TEMPORARY`, `REDIRECT_SCHEME_STATUS_CODE` and the comma-separated
+    /// `REDIRECT_SCHEME_IGNORE_PATHS` environment variables
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(RedirectConfig {
+            enable: env_bool("REDIRECT_SCHEME_ENABLE")?,
+            https_to_http: env_bool("REDIRECT_SCHEME_HTTPS_TO_HTTP")?,
+            temporary: env_bool("REDIRECT_SCHEME_TEMPORARY")?,
+            status_code: match env_var("REDIRECT_SCHEME_STATUS_CODE")? {
+                Some(value) => Some(value.parse().map_err(|_| {
+                    ConfigError::InvalidConfig(format!(
+                        "REDIRECT_SCHEME_STATUS_CODE: expected a status code, got {:?}",
+                        value
+                    ))
+                })?),
+                None => None,
+            },
+            replacements: Vec::new(),
+            ignore_paths: env_var("REDIRECT_SCHEME_IGNORE_PATHS")?
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|path| !path.is_empty())
+                        .map(str::to_owned)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Extract the configuration from a Figment, e.g. merging defaults, a file and the
+    /// environment
+    ///
+    /// Unknown keys are rejected, so providers like `Env` have to be limited to the known ones:
+    ///
+    /// ```rust
+    /// use actix_web_middleware_redirect_scheme::{ConfigError, RedirectConfig};
+    /// use figment::{providers::Env, Figment};
+    ///
+    /// let figment = Figment::from(RedirectConfig::default()).merge(
+    ///     Env::prefixed("REDIRECT_SCHEME_").only(&[
+    ///         "enable",
+    ///         "https_to_http",
+    ///         "temporary",
+    ///         "status_code",
+    ///     ]),
+    /// );
+    /// let config = RedirectConfig::from_figment(&figment)?;
+    /// # Ok::<(), ConfigError>(())
+    /// ```
+    #[cfg(feature = "figment-config")]
+    pub fn from_figment(figment: &Figment) -> Result<Self, ConfigError> {
+        figment
+            .extract()
+            .map_err(|e| ConfigError::InvalidConfig(e.to_string()))
+    }
+
+    /// Apply the configuration to the builder
+    pub fn apply(&self, builder: &mut RedirectSchemeBuilder) -> Result<(), ConfigError> {
+        if let Some(enable) = self.enable {
+            builder.enable(enable);
+        }
+        if let Some(https_to_http) = self.https_to_http {
+            builder.http_to_https(!https_to_http);
+        }
+        if let Some(temporary) = self.temporary {
+            builder.permanent(!temporary);
+        }
+        if let Some(code) = self.status_code {
+            match StatusCode::from_u16(code) {
+                Ok(status) if status.is_redirection() => {
+                    builder.status_code(status);
+                }
+                _ => return Err(ConfigError::InvalidStatusCode(code)),
+            }
+        }
+        if !self.replacements.is_empty() {
+            builder.replacements(&self.replacements);
+        }
+        builder.ignore_paths(&self.ignore_paths);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "figment-config")]
+impl Provider for RedirectConfig {
+    fn metadata(&self) -> Metadata {
+        Metadata::named("redirect scheme configuration")
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+        figment::providers::Serialized::defaults(self).data()
+    }
+}
+
+// Value of an environment variable, `None` if unset or empty
+fn env_var(name: &str) -> Result<Option<String>, ConfigError> {
+    match env::var(name) {
+        Ok(value) if value.trim().is_empty() => Ok(None),
+        Ok(value) => Ok(Some(value.trim().to_owned())),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(e) => Err(ConfigError::InvalidConfig(format!("{}: {}", name, e))),
+    }
+}
+
+// Boolean value of an environment variable like `true`, `1`, `yes` or `on`
+fn env_bool(name: &str) -> Result<Option<bool>, ConfigError> {
+    match env_var(name)? {
+        Some(value) => match value.to_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => Ok(Some(true)),
+            "false" | "0" | "no" | "off" => Ok(Some(false)),
+            _ => Err(ConfigError::InvalidConfig(format!(
+                "{}: expected a boolean, got {:?}",
+                name, value
+            ))),
+        },
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+
+/// Error in the configuration of the middleware
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// HSTS preload requested without a max age of at least one year and `includeSubDomains`
+    InvalidHstsPreload,
+    /// Ignore path pattern which can't be compiled
+    InvalidIgnorePattern(String),
+    /// Replacement with an empty pattern, an output which can't appear in a location, or
+    /// conflicting with another replacement
+    InvalidReplacement(String),
+    /// Configuration file which can't be read
+    UnreadableConfig(String),
+    /// Configuration document which can't be parsed, e.g. because of an unknown key
+    InvalidConfig(String),
+    /// Status code which is not a redirection
+    InvalidStatusCode(u16),
+    /// Redirection header whose name or value is not valid in a header
+    InvalidHeader(String),
+    /// Listener port which is zero or shared by the HTTP and HTTPS listeners
+    InvalidPort(u16),
+    /// Proxy or client network which is neither in CIDR notation nor an IP address
+    InvalidNetwork(String),
+    /// Setting which only applies to redirections from HTTP to HTTPS combined with the opposite
+    /// direction
+    ConflictingDirection(&'static str),
+    /// Settings contradicting each other, of which only one would take effect
+    ConflictingSettings(&'static str, &'static str),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::InvalidHstsPreload => write!(
+                f,
+                "HSTS preload requires a max age of at least one year and includeSubDomains"
+            ),
+            ConfigError::InvalidIgnorePattern(e) => write!(f, "invalid ignore path pattern: {}", e),
+            ConfigError::InvalidReplacement(e) => write!(f, "invalid replacement: {}", e),
+            ConfigError::UnreadableConfig(e) => write!(f, "unreadable configuration: {}", e),
+            ConfigError::InvalidConfig(e) => write!(f, "invalid configuration: {}", e),
+            ConfigError::InvalidStatusCode(code) => {
+                write!(f, "status code is not a redirection: {}", code)
+            }
+            ConfigError::InvalidHeader(header) => write!(f, "invalid header: {}", header),
+            ConfigError::InvalidPort(port) => write!(
+                f,
+                "invalid listener port {}, ports must be non-zero and differ between HTTP and HTTPS",
+                port
+            ),
+            ConfigError::InvalidNetwork(value) => write!(f, "invalid network: {}", value),
+            ConfigError::ConflictingSettings(first, second) => {
+                write!(f, "{} conflicts with {}, set only one of them", first, second)
+            }
+            ConfigError::ConflictingDirection(setting) => write!(
+                f,
+                "{} only applies to redirections from HTTP to HTTPS, not from HTTPS to HTTP",
+                setting
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
@@ -0,0 +1,129 @@
+use actix_web::dev::ServiceRequest;
+use futures::future::LocalBoxFuture;
+use std::sync::Arc;
+
+/// Synchronous resolver of the request scheme
+pub type SchemeResolver = Arc<dyn Fn(&ServiceRequest) -> Option<SchemeHint> + Send + Sync>;
+
+/// Asynchronous resolver of the request scheme
+pub type AsyncSchemeResolver =
+    Arc<dyn Fn(&ServiceRequest) -> LocalBoxFuture<'static, Option<SchemeHint>> + Send + Sync>;
+
+/// Scheme of the request as determined by application code, e.g. by an earlier middleware or an
+/// `on_connect` callback parsing the PROXY protocol.
+///
+/// When present in the request extensions it takes precedence over `ConnectionInfo`.
+///
+/// ## Usage
+/// ```
+/// extern crate actix_web_middleware_redirect_scheme;
+///
+/// use actix_web::{dev::Service, App, web, HttpMessage, HttpResponse};
+/// use actix_web_middleware_redirect_scheme::{RedirectSchemeBuilder, SchemeHint};
+///
+/// App::new()
+///     .wrap(RedirectSchemeBuilder::new().build())
+///     .wrap_fn(|req, srv| {
+///         req.extensions_mut().insert(SchemeHint::Https);
+///         srv.call(req)
+///     })
+///     .route("/", web::get().to(|| HttpResponse::Ok()
+///                                     .content_type("text/plain")
+///                                     .body("Always HTTPS!")));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchemeHint {
+    /// Request arrived over plain HTTP
+    Http,
+    /// Request arrived over HTTPS
+    Https,
+}
+
+impl SchemeHint {
+    /// Scheme name of the hint
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SchemeHint::Http => "http",
+            SchemeHint::Https => "https",
+        }
+    }
+}
+
+/// Connection facts reported by a PROXY protocol (v1/v2) parser, e.g. stored in the request
+/// extensions by an `on_connect` callback.
+///
+/// When present in the request extensions it is used to determine the scheme, unless a
+/// `SchemeHint` is present as well.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProxyProtocolInfo {
+    /// Whether the client connection to the load balancer used TLS (v2 `PP2_TYPE_SSL`)
+    pub tls: Option<bool>,
+    /// Port the client connected to on the load balancer
+    pub destination_port: Option<u16>,
+}
+
+impl ProxyProtocolInfo {
+    /// Scheme implied by the connection facts, `None` when unknown
+    pub fn scheme(&self) -> Option<&'static str> {
+        match (self.tls, self.destination_port) {
+            (Some(true), _) | (None, Some(443)) => Some("https"),
+            (Some(false), _) | (None, Some(80)) => Some("http"),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with requests whose scheme can't be determined, i.e. requests without scheme hint or
+/// forwarded headers arriving on a plain listener.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Assume {
+    /// Treat the request as plain HTTP
+    #[default]
+    Http,
+    /// Treat the request as HTTPS
+    Https,
+    /// Never redirect the request
+    PassThrough,
+}
+
+/// Source of the request scheme.
+///
+/// Sources relying on headers are only consulted for trusted peers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchemeSource {
+    /// `actix-web` connection info, which also honors forwarded headers
+    ConnectionInfo,
+    /// `proto` parameter of the RFC 7239 `Forwarded` header
+    ForwardedHeader,
+    /// `X-Forwarded-Proto` header
+    XForwardedProto,
+    /// Headers configured by `scheme_header`, `https_header` and the proxy presets
+    CustomHeader,
+    /// TLS state of the listener the request arrived on
+    TlsListener,
+    /// `SchemeHint` or `ProxyProtocolInfo` stored in the request extensions
+    Extension,
+}
+
+/// Interpretation of an `X-Forwarded-Proto` header listing several schemes, e.g. `https, http`
+/// after passing multiple proxies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ForwardedProtoPolicy {
+    /// Use the scheme added by the outermost proxy
+    #[default]
+    First,
+    /// Use the scheme added by the innermost proxy
+    Last,
+    /// Treat the request as HTTPS only when every hop used HTTPS
+    RequireAllHttps,
+}
+
+/// Source of the host of the redirection target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HostSource {
+    /// `actix-web` connection info, which also honors forwarded headers of trusted proxies
+    #[default]
+    ConnectionInfo,
+    /// Literal `Host` header of the request
+    HostHeader,
+}
@@ -87,6 +87,108 @@
 //!                                     .body("Always HTTPS on non-default ports!")));
 //! ```
 //!
+//! When the application is reachable directly (not only through a proxy), clients can spoof `X-Forwarded-Proto`.
+//! Restrict forwarded scheme headers to your proxies, other peers are judged by the raw connection scheme:
+//!
+//! ```rust
+//! use actix_web::{App, web, HttpResponse};
+//! use actix_web_middleware_redirect_scheme::RedirectSchemeBuilder;
+//!
+//! App::new()
+//!     .wrap(RedirectSchemeBuilder::new().trusted_proxies(&["10.0.0.0/8".parse().unwrap()]).build())
+//!     .route("/", web::get().to(|| HttpResponse::Ok()
+//!                                     .content_type("text/plain")
+//!                                     .body("Always HTTPS behind trusted proxies!")));
+//! ```
+//!
+//! The RFC 7239 `Forwarded` header takes precedence over the legacy `X-Forwarded-*` headers. Proxies that pass a client supplied `Forwarded` header through while setting `X-Forwarded-*` themselves need the opposite order:
+//!
+//! ```rust
+//! use actix_web::{App, web, HttpResponse};
+//! use actix_web_middleware_redirect_scheme::RedirectSchemeBuilder;
+//!
+//! App::new()
+//!     .wrap(RedirectSchemeBuilder::new().prefer_forwarded(false).build())
+//!     .route("/", web::get().to(|| HttpResponse::Ok()
+//!                                     .content_type("text/plain")
+//!                                     .body("Always HTTPS behind legacy proxies!")));
+//! ```
+//!
+//! Instead of string replacements, the ports of both listeners can be registered and are swapped in the host of the redirection, leaving the path and query untouched:
+//!
+//! ```rust
+//! use actix_web::{App, web, HttpResponse};
+//! use actix_web_middleware_redirect_scheme::RedirectSchemeBuilder;
+//!
+//! App::new()
+//!     .wrap(RedirectSchemeBuilder::new().http_port(8080).https_port(8443).build())
+//!     .route("/", web::get().to(|| HttpResponse::Ok()
+//!                                     .content_type("text/plain")
+//!                                     .body("Always HTTPS on non-default ports!")));
+//! ```
+//!
+//! Security-sensitive APIs may prefer failing closed, answering with an error instead of redirecting:
+//!
+//! ```rust
+//! use actix_web::{App, http::StatusCode, web, HttpResponse};
+//! use actix_web_middleware_redirect_scheme::RedirectSchemeBuilder;
+//!
+//! App::new()
+//!     .wrap(RedirectSchemeBuilder::new().reject(StatusCode::FORBIDDEN, "HTTPS is required").build())
+//!     .route("/", web::get().to(|| HttpResponse::Ok()
+//!                                     .content_type("text/plain")
+//!                                     .body("HTTPS only!")));
+//! ```
+//!
+//! The middleware can also add a `Strict-Transport-Security` header to responses of requests which already use HTTPS:
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use actix_web::{App, web, HttpResponse};
+//! use actix_web_middleware_redirect_scheme::RedirectSchemeBuilder;
+//!
+//! App::new()
+//!     .wrap(RedirectSchemeBuilder::new()
+//!               .hsts(Duration::from_secs(31_536_000))
+//!               .hsts_include_subdomains(true)
+//!               .try_build()
+//!               .unwrap())
+//!     .route("/", web::get().to(|| HttpResponse::Ok()
+//!                                     .content_type("text/plain")
+//!                                     .body("Always HTTPS, remembered by browsers!")));
+//! ```
+//!
+//! Some paths, like ACME challenges or health checks, must stay reachable over plain HTTP. Ignore them by prefix or glob pattern, and re-include paths under an ignored prefix:
+//!
+//! ```rust
+//! use actix_web::{App, web, HttpResponse};
+//! use actix_web_middleware_redirect_scheme::RedirectSchemeBuilder;
+//!
+//! App::new()
+//!     .wrap(RedirectSchemeBuilder::new()
+//!               .ignore_path("/.well-known/acme-challenge/")
+//!               .ignore_path_glob("/static/**/*.css")
+//!               .ignore_path("/api/")
+//!               .redirect_path("/api/login")
+//!               .build())
+//!     .route("/", web::get().to(|| HttpResponse::Ok()
+//!                                     .content_type("text/plain")
+//!                                     .body("Mostly HTTPS!")));
+//! ```
+//!
+//! Redirecting a request with a body loses the body or changes its semantics for many clients. Redirect only idempotent methods and let the application decide about the others:
+//!
+//! ```rust
+//! use actix_web::{App, http::Method, web, HttpResponse};
+//! use actix_web_middleware_redirect_scheme::RedirectSchemeBuilder;
+//!
+//! App::new()
+//!     .wrap(RedirectSchemeBuilder::new().redirect_methods(&[Method::GET, Method::HEAD]).build())
+//!     .route("/", web::get().to(|| HttpResponse::Ok()
+//!                                     .content_type("text/plain")
+//!                                     .body("HTTPS for GET and HEAD!")));
+//! ```
+//!
 //! ### Usage HTTPS -> HTTP
 //!
 //! ```toml
@@ -131,8 +233,33 @@
 //! ```
 
 pub mod builder;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod config;
+pub mod error;
+pub mod hint;
+pub mod matcher;
+pub mod policy;
+pub mod responder;
 pub mod scheme;
 pub mod service;
+#[cfg(feature = "watch")]
+pub mod watch;
 
 pub use crate::builder::RedirectSchemeBuilder;
+#[cfg(feature = "cli")]
+pub use crate::cli::RedirectSchemeArgs;
+pub use crate::config::RedirectConfig;
+pub use crate::error::ConfigError;
+pub use crate::hint::{
+    Assume, ForwardedProtoPolicy, HostSource, ProxyProtocolInfo, SchemeHint, SchemeSource,
+};
+pub use crate::matcher::{GlobPattern, HostPattern, PathPattern, PathSet};
+pub use crate::policy::{
+    H2cPolicy, LocationEncoding, LocationSerializer, Profile, RedirectBody, RedirectHook,
+    RedirectPredicate, ReplacementScope, UrlMapper, WebSocketPolicy, WwwPolicy,
+};
+pub use crate::responder::RedirectResponder;
 pub use crate::scheme::RedirectScheme;
+#[cfg(feature = "watch")]
+pub use crate::watch::WatchedRedirectScheme;
@@ -0,0 +1,402 @@
+use actix_web::dev::ResourceDef;
+use std::collections::BTreeMap;
+
+/// Pattern matched against request paths
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathPattern {
+    /// Paths equal to the string
+    Exact(String),
+    /// Paths starting with the prefix
+    Prefix(String),
+    /// Paths matching the glob pattern
+    Glob(GlobPattern),
+    /// Paths matching the actix route definition
+    Route(ResourceDef),
+}
+
+impl PathPattern {
+    /// Whether the path matches the pattern
+    pub fn matches(&self, path: &str) -> bool {
+        match self {
+            PathPattern::Exact(exact) => path == exact,
+            PathPattern::Prefix(prefix) => path.starts_with(prefix.as_str()),
+            PathPattern::Glob(glob) => glob.matches(path),
+            PathPattern::Route(route) => route.is_match(path),
+        }
+    }
+}
+
+/// Set of path patterns compiled for matching, exact paths and prefixes are looked up in a trie
+/// in time proportional to the path length regardless of their number.
+///
+/// Excluded patterns take paths out of the set, the most specific matching rule wins. Exact paths
+/// are the most specific, other rules are ranked by their number of literal characters, so the
+/// glob `/api/*/login` is more specific than the prefix `/api/`. Of equally specific rules,
+/// exclusions win.
+#[derive(Clone, Debug, Default)]
+pub struct PathSet {
+    nodes: Vec<Node>,
+    patterns: Vec<(PathPattern, bool)>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Node {
+    children: BTreeMap<u8, usize>,
+    exact: Option<bool>,
+    prefix: Option<bool>,
+}
+
+impl PathSet {
+    /// Compile the patterns and the patterns excluded from them
+    pub fn new(patterns: &[PathPattern], excluded: &[PathPattern]) -> Self {
+        let mut set = PathSet {
+            nodes: vec![Node::default()],
+            patterns: Vec::new(),
+        };
+        for (pattern, included) in excluded
+            .iter()
+            .map(|pattern| (pattern, false))
+            .chain(patterns.iter().map(|pattern| (pattern, true)))
+        {
+            match pattern {
+                PathPattern::Exact(exact) => {
+                    let node = set.insert(exact);
+                    set.nodes[node].exact.get_or_insert(included);
+                }
+                PathPattern::Prefix(prefix) => {
+                    let node = set.insert(prefix);
+                    set.nodes[node].prefix.get_or_insert(included);
+                }
+                pattern => set.patterns.push((pattern.clone(), included)),
+            }
+        }
+        set
+    }
+
+    fn insert(&mut self, path: &str) -> usize {
+        let mut node = 0;
+        for byte in path.bytes() {
+            node = match self.nodes[node].children.get(&byte) {
+                Some(&child) => child,
+                None => {
+                    self.nodes.push(Node::default());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[node].children.insert(byte, child);
+                    child
+                }
+            };
+        }
+        node
+    }
+
+    /// Whether the path matches the set
+    pub fn matches(&self, path: &str) -> bool {
+        self.patterns
+            .iter()
+            .filter(|(pattern, _)| pattern.matches(path))
+            .map(|(pattern, included)| (specificity(pattern), *included))
+            .chain(self.lookup(path))
+            .max_by_key(|&(specificity, included)| (specificity, !included))
+            .is_some_and(|(_, included)| included)
+    }
+
+    // Specificity and verdict of the most specific exact path or prefix
+    fn lookup(&self, path: &str) -> Option<(usize, bool)> {
+        let mut node = self.nodes.first()?;
+        let mut verdict = None;
+        for (depth, byte) in path.bytes().enumerate() {
+            if let Some(included) = node.prefix {
+                verdict = Some((depth, included));
+            }
+            node = match node.children.get(&byte) {
+                Some(&child) => &self.nodes[child],
+                None => return verdict,
+            };
+        }
+        match (node.exact, node.prefix) {
+            (Some(included), _) => Some((usize::MAX, included)),
+            (None, Some(included)) => Some((path.len(), included)),
+            (None, None) => verdict,
+        }
+    }
+}
+
+// Number of literal characters of a glob or route pattern, exact paths are the most specific
+fn specificity(pattern: &PathPattern) -> usize {
+    match pattern {
+        PathPattern::Exact(_) => usize::MAX,
+        PathPattern::Prefix(prefix) => prefix.len(),
+        PathPattern::Glob(glob) => glob
+            .tokens
+            .iter()
+            .filter(|token| matches!(token, Token::Literal(_)))
+            .count(),
+        PathPattern::Route(route) => {
+            let mut depth = 0;
+            route
+                .pattern()
+                .chars()
+                .filter(|&c| {
+                    match c {
+                        '{' => depth += 1,
+                        '}' => depth -= 1,
+                        '*' => return false,
+                        _ => return depth == 0,
+                    }
+                    false
+                })
+                .count()
+        }
+    }
+}
+
+/// Glob pattern compiled for matching request paths.
+///
+/// `?` matches a single character and `*` any sequence of characters within a path segment,
+/// `**` matches any sequence of characters across segments, and `**/` any number of whole
+/// segments including none.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlobPattern {
+    pattern: String,
+    tokens: Vec<Token>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Literal(char),
+    AnyChar,
+    AnyInSegment,
+    Any,
+    AnySegments,
+}
+
+impl GlobPattern {
+    /// Compile a glob pattern
+    pub fn new(pattern: &str) -> Self {
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '?' => tokens.push(Token::AnyChar),
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        tokens.push(Token::AnySegments);
+                    } else {
+                        tokens.push(Token::Any);
+                    }
+                }
+                '*' => tokens.push(Token::AnyInSegment),
+                c => tokens.push(Token::Literal(c)),
+            }
+        }
+        GlobPattern {
+            pattern: pattern.to_owned(),
+            tokens,
+        }
+    }
+
+    /// Source of the pattern
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Whether the whole path matches the pattern
+    pub fn matches(&self, path: &str) -> bool {
+        match_tokens(&self.tokens, path)
+    }
+}
+
+fn match_tokens(tokens: &[Token], path: &str) -> bool {
+    let (token, rest) = match tokens.split_first() {
+        Some(split) => split,
+        None => return path.is_empty(),
+    };
+    match token {
+        Token::Literal(c) => path.starts_with(*c) && match_tokens(rest, &path[c.len_utf8()..]),
+        Token::AnyChar => match path.chars().next() {
+            Some(c) if c != '/' => match_tokens(rest, &path[c.len_utf8()..]),
+            _ => false,
+        },
+        Token::AnyInSegment => {
+            let end = path.find('/').unwrap_or(path.len());
+            path[..end]
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain(std::iter::once(end))
+                .any(|i| match_tokens(rest, &path[i..]))
+        }
+        Token::Any => path
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(path.len()))
+            .any(|i| match_tokens(rest, &path[i..])),
+        Token::AnySegments => {
+            match_tokens(rest, path)
+                || path
+                    .match_indices('/')
+                    .any(|(i, _)| match_tokens(rest, &path[i + 1..]))
+        }
+    }
+}
+
+/// Pattern matched against request hosts, either a host name or a wildcard like `*.example.com`
+/// matching all its subdomains
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HostPattern {
+    /// Host equal to the name
+    Exact(String),
+    /// Host ending with the suffix, including its leading dot
+    Suffix(String),
+}
+
+impl HostPattern {
+    /// Compile a host name or wildcard pattern
+    pub fn new(pattern: &str) -> Self {
+        let pattern = pattern.to_lowercase();
+        if pattern.starts_with("*.") {
+            HostPattern::Suffix(pattern[1..].to_owned())
+        } else {
+            HostPattern::Exact(pattern)
+        }
+    }
+
+    /// Whether the host name, without port, matches the pattern
+    pub fn matches(&self, host: &str) -> bool {
+        match self {
+            HostPattern::Exact(name) => host.eq_ignore_ascii_case(name),
+            HostPattern::Suffix(suffix) => {
+                host.len() > suffix.len()
+                    && host.is_char_boundary(host.len() - suffix.len())
+                    && host[host.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefix(value: &str) -> PathPattern {
+        PathPattern::Prefix(value.to_owned())
+    }
+
+    fn glob(value: &str) -> PathPattern {
+        PathPattern::Glob(GlobPattern::new(value))
+    }
+
+    #[test]
+    fn glob_wildcards() {
+        let pattern = GlobPattern::new("/static/**/*.css");
+        assert!(pattern.matches("/static/site.css"));
+        assert!(pattern.matches("/static/a/b/site.css"));
+        assert!(!pattern.matches("/static/site.js"));
+        assert!(!pattern.matches("/assets/site.css"));
+
+        let pattern = GlobPattern::new("/users/*/avatar");
+        assert!(pattern.matches("/users/42/avatar"));
+        assert!(pattern.matches("/users//avatar"));
+        assert!(!pattern.matches("/users/42/43/avatar"));
+
+        let pattern = GlobPattern::new("/v?/**");
+        assert!(pattern.matches("/v1/"));
+        assert!(pattern.matches("/v2/a/b"));
+        assert!(!pattern.matches("/v/"));
+        assert!(!pattern.matches("/v12/a"));
+        assert_eq!(pattern.as_str(), "/v?/**");
+    }
+
+    #[test]
+    fn glob_unicode() {
+        let pattern = GlobPattern::new("/caf?/*");
+        assert!(pattern.matches("/café/menü"));
+        assert!(!pattern.matches("/cafe"));
+    }
+
+    #[test]
+    fn host_patterns() {
+        let exact = HostPattern::new("Example.com");
+        assert_eq!(exact, HostPattern::Exact("example.com".to_owned()));
+        assert!(exact.matches("EXAMPLE.com"));
+        assert!(!exact.matches("www.example.com"));
+
+        let wildcard = HostPattern::new("*.example.com");
+        assert_eq!(wildcard, HostPattern::Suffix(".example.com".to_owned()));
+        assert!(wildcard.matches("www.example.com"));
+        assert!(wildcard.matches("a.b.EXAMPLE.COM"));
+        assert!(!wildcard.matches("example.com"));
+        assert!(!wildcard.matches(".example.com"));
+        assert!(!wildcard.matches("badexample.com"));
+        assert!(!wildcard.matches("ééexample.com"));
+    }
+
+    #[test]
+    fn path_set_prefixes_and_exact_paths() {
+        let set = PathSet::new(
+            &[
+                prefix("/api/"),
+                PathPattern::Exact("/health".to_owned()),
+                glob("/*.txt"),
+            ],
+            &[],
+        );
+        assert!(set.matches("/api/"));
+        assert!(set.matches("/api/users"));
+        assert!(set.matches("/health"));
+        assert!(set.matches("/robots.txt"));
+        assert!(!set.matches("/health/live"));
+        assert!(!set.matches("/ap"));
+        assert!(!set.matches("/"));
+        assert!(!PathSet::default().matches("/api/"));
+    }
+
+    #[test]
+    fn path_set_most_specific_rule_wins() {
+        let set = PathSet::new(
+            &[prefix("/api/"), prefix("/api/login/callback/")],
+            &[prefix("/api/login/"), glob("/api/*/secret")],
+        );
+        assert!(set.matches("/api/users"));
+        assert!(!set.matches("/api/login/"));
+        assert!(set.matches("/api/login/callback/github"));
+        assert!(!set.matches("/api/users/secret"));
+    }
+
+    #[test]
+    fn path_set_rules_under_prefix() {
+        let set = PathSet::new(&[prefix("/static/")], &[glob("/static/*/index.html")]);
+        assert!(set.matches("/static/app.js"));
+        assert!(!set.matches("/static/docs/index.html"));
+
+        let set = PathSet::new(
+            &[PathPattern::Route(ResourceDef::new("/files/{name}/raw"))],
+            &[prefix("/files/")],
+        );
+        assert!(set.matches("/files/report/raw"));
+        assert!(!set.matches("/files/report"));
+    }
+
+    #[test]
+    fn path_set_exclusions_win_ties() {
+        let set = PathSet::new(&[prefix("/api/")], &[prefix("/api/")]);
+        assert!(!set.matches("/api/users"));
+
+        let set = PathSet::new(
+            &[PathPattern::Exact("/login".to_owned())],
+            &[PathPattern::Exact("/login".to_owned())],
+        );
+        assert!(!set.matches("/login"));
+    }
+
+    #[test]
+    fn exact_paths_beat_prefixes() {
+        let set = PathSet::new(
+            &[PathPattern::Exact("/api/login".to_owned())],
+            &[prefix("/api/")],
+        );
+        assert!(set.matches("/api/login"));
+        assert!(!set.matches("/api/login/"));
+    }
+}
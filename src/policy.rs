@@ -0,0 +1,109 @@
+use actix_web::dev::ServiceRequest;
+use actix_web::http::StatusCode;
+use actix_web::HttpResponse;
+use std::sync::Arc;
+
+/// Hook inspecting the request and mutating the generated response before it is returned
+pub type RedirectHook = Arc<dyn Fn(&ServiceRequest, &mut HttpResponse) + Send + Sync>;
+
+/// Predicate deciding whether a request may be redirected
+pub type RedirectPredicate = Arc<dyn Fn(&ServiceRequest) -> bool + Send + Sync>;
+
+/// Closure transforming the built location of a request
+pub type UrlMapper = Arc<dyn Fn(String, &ServiceRequest) -> String + Send + Sync>;
+
+/// Serializer of the new location into a JSON redirection body
+pub type LocationSerializer = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Handling of HTTP/2 cleartext (h2c) requests, whose clients can't follow a redirection to
+/// another scheme.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum H2cPolicy {
+    /// Redirect like any other request
+    #[default]
+    Redirect,
+    /// Never redirect the request
+    PassThrough,
+    /// Answer with the given status code, e.g. `505 HTTP Version Not Supported`
+    Respond(StatusCode),
+}
+
+/// Handling of WebSocket handshakes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WebSocketPolicy {
+    /// Redirect to the `http://` or `https://` location like any other request
+    #[default]
+    Redirect,
+    /// Redirect to the `ws://` or `wss://` location
+    RedirectWebSocket,
+    /// Never redirect the request
+    PassThrough,
+}
+
+/// Body of redirection responses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RedirectBody {
+    /// No body
+    #[default]
+    Empty,
+    /// HTML page with a link to the new location
+    Html,
+    /// Plain text naming the new location
+    Text,
+    /// JSON object naming the new location
+    Json,
+    /// JSON, HTML or no body, depending on the `Accept` header of the request
+    Negotiate,
+}
+
+/// Canonicalization of the `www.` prefix of the redirection host.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WwwPolicy {
+    /// Keep the host as requested
+    #[default]
+    Keep,
+    /// Redirect `example.com` to `www.example.com`
+    Add,
+    /// Redirect `www.example.com` to `example.com`
+    Remove,
+}
+
+/// Handling of characters which can't appear in the path or query of a location, like spaces and
+/// control characters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LocationEncoding {
+    /// Percent-encode the characters
+    #[default]
+    Encode,
+    /// Answer the request with `400 Bad Request` instead of a redirection
+    Reject,
+}
+
+/// Component of the location a replacement applies to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReplacementScope {
+    /// The whole location, like replacements without a scope
+    #[default]
+    Whole,
+    /// The host including the port
+    Host,
+    /// The path
+    Path,
+    /// The query string
+    Query,
+}
+
+/// Preset of coherent settings for a deployment environment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// Temporary redirections between the ports 8080 and 8443, never redirecting loopback clients
+    Dev,
+    /// Temporary redirections with a short-lived HSTS header
+    Staging,
+    /// Permanent redirections preserving the method, HSTS for a year, lowercased hosts,
+    /// "400 Bad Request" for requests without host or with userinfo, and
+    /// "421 Misdirected Request" for hosts outside `only_hosts` once they are set
+    ///
+    /// HSTS doesn't cover subdomains, add `hsts_include_subdomains` once all of them serve HTTPS.
+    Prod,
+}
@@ -0,0 +1,44 @@
+use actix_web::{dev::ServiceRequest, HttpResponse};
+
+/// Producer of the response redirecting a request to its target, for full control over the
+/// response shape while reusing the decision and URL building of the middleware.
+///
+/// When no responder is configured, the middleware answers with the configured status code,
+/// headers and body.
+///
+/// ## Usage
+/// ```
+/// extern crate actix_web_middleware_redirect_scheme;
+///
+/// use actix_web::{dev::ServiceRequest, http, App, web, HttpResponse};
+/// use actix_web_middleware_redirect_scheme::{RedirectResponder, RedirectSchemeBuilder};
+///
+/// struct Found;
+///
+/// impl RedirectResponder for Found {
+///     fn respond(&self, _req: &ServiceRequest, target: &str) -> HttpResponse {
+///         HttpResponse::Found()
+///             .header(http::header::LOCATION, target)
+///             .finish()
+///     }
+/// }
+///
+/// App::new()
+///     .wrap(RedirectSchemeBuilder::new().responder(Found).build())
+///     .route("/", web::get().to(|| HttpResponse::Ok()
+///                                     .content_type("text/plain")
+///                                     .body("Always HTTPS!")));
+/// ```
+pub trait RedirectResponder: Send + Sync {
+    /// Response redirecting the request to the target URL
+    fn respond(&self, req: &ServiceRequest, target: &str) -> HttpResponse;
+}
+
+impl<F> RedirectResponder for F
+where
+    F: Fn(&ServiceRequest, &str) -> HttpResponse + Send + Sync,
+{
+    fn respond(&self, req: &ServiceRequest, target: &str) -> HttpResponse {
+        self(req, target)
+    }
+}
@@ -1,8 +1,27 @@
+use crate::hint::{
+    Assume, AsyncSchemeResolver, ForwardedProtoPolicy, HostSource, SchemeResolver, SchemeSource,
+};
+use crate::matcher::{HostPattern, PathPattern, PathSet};
+use crate::policy::{
+    H2cPolicy, LocationEncoding, LocationSerializer, RedirectBody, RedirectHook, RedirectPredicate,
+    ReplacementScope, UrlMapper, WebSocketPolicy, WwwPolicy,
+};
+use crate::responder::RedirectResponder;
 use crate::service::RedirectSchemeService;
 use actix_service::{Service, Transform};
 use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::normalize::TrailingSlash;
 use actix_web::Error;
 use futures::future::{ok, Ready};
+use ipnet::IpNet;
+#[cfg(feature = "regex")]
+use regex::RegexSet;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Middleware for `actix-web` which redirects between `http` and `https` requests with optional url
 /// string replacements.
@@ -30,6 +49,168 @@ pub struct RedirectScheme {
     pub temporary: bool,
     // List of string replacements
     pub replacements: Vec<(String, String)>,
+    // Proxies allowed to set forwarded scheme headers (empty: trust everyone)
+    pub trusted_proxies: Vec<IpNet>,
+    // Prefer legacy `X-Forwarded-*` headers over RFC 7239 `Forwarded` header
+    pub prefer_x_forwarded: bool,
+    // Custom header carrying the original scheme
+    pub scheme_header: Option<String>,
+    // Read the original scheme from Cloudflare's `CF-Visitor` header
+    pub cf_visitor: bool,
+    // Header whose presence marks the request as HTTPS
+    pub https_header: Option<String>,
+    // Scheme assumed when the request does not reveal it
+    pub unknown_scheme_policy: Assume,
+    // Sources of the request scheme in order of priority (empty: default order)
+    pub scheme_sources: Vec<SchemeSource>,
+    // Handling of HTTP/2 cleartext requests
+    pub h2c_policy: H2cPolicy,
+    // Handling of WebSocket handshakes
+    pub websocket_policy: WebSocketPolicy,
+    // Interpretation of multi-valued `X-Forwarded-Proto` headers
+    pub forwarded_proto_policy: ForwardedProtoPolicy,
+    // Build the redirection host from `X-Forwarded-Host`
+    pub forwarded_host: bool,
+    // Synchronous resolver of the request scheme
+    pub scheme_resolver: Option<SchemeResolver>,
+    // Asynchronous resolver of the request scheme
+    pub async_scheme_resolver: Option<AsyncSchemeResolver>,
+    // Redirect only requests carrying `Upgrade-Insecure-Requests: 1`
+    pub upgrade_insecure_only: bool,
+    // Status code for requests carrying `Upgrade-Insecure-Requests: 1`
+    pub upgrade_insecure_status: Option<StatusCode>,
+    // Status code for requests not to be upgraded (None: pass through)
+    pub insecure_response: Option<StatusCode>,
+    // Port of the HTTP listener
+    pub http_port: Option<u16>,
+    // Port of the HTTPS listener
+    pub https_port: Option<u16>,
+    // Source of the host of the redirection target
+    pub host_source: HostSource,
+    // Permanent redirect preserving the method (true: 308 Permanent Redirect, false: 301 Moved Permanently)
+    pub preserve_method: bool,
+    // Answer code of redirections, overriding permanent and temporary flags
+    pub status_code: Option<StatusCode>,
+    // Answer code of redirections for unsafe methods like POST
+    pub unsafe_methods_status: Option<StatusCode>,
+    // Answer code and body rejecting requests instead of redirecting them
+    pub reject: Option<(StatusCode, String)>,
+    // Answer "426 Upgrade Required" instead of redirecting to HTTPS
+    pub upgrade_required: bool,
+    // Body of redirection responses
+    pub redirect_body: RedirectBody,
+    // Serializer of JSON redirection bodies
+    pub json_serializer: Option<LocationSerializer>,
+    // Additional headers of redirection responses
+    pub redirect_headers: Vec<(HeaderName, HeaderValue)>,
+    // `Cache-Control` header of redirections
+    pub cache_control: Option<HeaderValue>,
+    // Max age of the `Strict-Transport-Security` header added to HTTPS responses
+    pub hsts_max_age: Option<Duration>,
+    // Add `includeSubDomains` to the `Strict-Transport-Security` header
+    pub hsts_include_subdomains: bool,
+    // Add `preload` to the `Strict-Transport-Security` header
+    pub hsts_preload: bool,
+    // Add `Refresh` header to redirections
+    pub refresh_header: bool,
+    // Add `<meta http-equiv="refresh">` to HTML bodies of redirections
+    pub meta_refresh: bool,
+    // Add `Link: <...>; rel="canonical"` header to redirections
+    pub canonical_link: bool,
+    // Add `Link: <...>; rel="canonical"` header to responses passed through
+    pub canonical_link_pass_through: bool,
+    // Hook mutating generated responses before they are returned
+    pub on_redirect: Option<RedirectHook>,
+    // Largest request body redirected and answer code for larger ones
+    pub body_limit: Option<(u64, StatusCode)>,
+    // Producer of redirection responses (None: built-in)
+    pub responder: Option<Arc<dyn RedirectResponder>>,
+    // Methods of redirected requests (empty: all methods)
+    pub redirect_methods: Vec<Method>,
+    // Add `X-Redirected-By` header to generated responses
+    pub diagnostic_header: bool,
+    // Answer codes of redirections for path prefixes
+    pub path_statuses: Vec<(String, StatusCode)>,
+    // Paths never redirected
+    pub ignore_paths: Vec<PathPattern>,
+    // Regular expressions of paths never redirected
+    #[cfg(feature = "regex")]
+    pub ignore_path_regex: Option<RegexSet>,
+    // Paths redirected exclusively (empty: all paths)
+    pub only_paths: Vec<PathPattern>,
+    // File extensions of paths never redirected
+    pub ignore_extensions: Vec<String>,
+    // Methods of requests never redirected
+    pub ignore_methods: Vec<Method>,
+    // Header skipping the redirection, with optional required value
+    pub bypass_header: Option<(String, Option<String>)>,
+    // User agent substrings of requests never redirected (lowercase)
+    pub bypass_user_agents: Vec<String>,
+    // Hosts redirected exclusively (empty: all hosts)
+    pub only_hosts: Vec<HostPattern>,
+    // Answer code for hosts not redirected exclusively (None: pass through)
+    pub unknown_host_status: Option<StatusCode>,
+    // Hosts never redirected
+    pub ignore_hosts: Vec<HostPattern>,
+    // Configurations for specific hosts
+    pub host_policies: Vec<(HostPattern, RedirectScheme)>,
+    // Networks of clients which are never redirected
+    pub bypass_cidrs: Vec<IpNet>,
+    // Never redirect requests from loopback clients
+    pub skip_loopback: bool,
+    // Name and optional secret value of a query parameter skipping the redirection
+    pub bypass_query_param: Option<(String, Option<String>)>,
+    // Name of a cookie skipping the redirection
+    pub bypass_cookie: Option<String>,
+    // Predicates which must all hold for a request to be redirected
+    pub predicates: Vec<RedirectPredicate>,
+    // Predicates forcing the redirection of a request despite ignore and bypass rules
+    pub force_predicates: Vec<RedirectPredicate>,
+    // Paths redirected even though matching ignored paths
+    pub redirect_paths: Vec<PathPattern>,
+    // Redirect only top-level navigations according to `Sec-Fetch-Dest`
+    pub navigations_only: bool,
+    // Host of every redirection target instead of the requested one
+    pub canonical_host: Option<String>,
+    // Canonicalization of the `www.` prefix of the redirection host
+    pub www_policy: WwwPolicy,
+    // Host names replaced in the redirection target
+    pub host_rewrites: Vec<(String, String)>,
+    // Drop the query string from the redirection target
+    pub strip_query: bool,
+    // Query parameters appended to the redirection target
+    pub query_params: Vec<(String, String)>,
+    // Path prefixes replaced in the redirection target
+    pub path_rewrites: Vec<(String, String)>,
+    // Trailing slash normalization of the redirection target
+    pub trailing_slash: Option<TrailingSlash>,
+    // Keep the path of the redirection target as requested, without normalization
+    pub raw_path: bool,
+    // Lowercase the host of the redirection target
+    pub lowercase_host: bool,
+    // Status code answered to redirected requests for hosts with uppercase letters
+    pub mixed_case_host_status: Option<StatusCode>,
+    // Convert Unicode host names of the redirection target to punycode
+    #[cfg(feature = "idna")]
+    pub punycode_host: bool,
+    // Handling of characters invalid in the path or query of the redirection target
+    pub location_encoding: LocationEncoding,
+    // String replacements applied to a component of the location
+    pub scoped_replacements: Vec<(ReplacementScope, String, String)>,
+    // Template of the redirection target
+    pub location_template: Option<String>,
+    // Closure transforming the built location
+    pub map_url: Option<UrlMapper>,
+    // Host of the redirection target of requests without host
+    pub fallback_host: Option<String>,
+    // Status code answered to redirected requests without host
+    pub missing_host_status: Option<StatusCode>,
+    // Status code answered to redirected requests for hosts with userinfo
+    pub userinfo_status: Option<StatusCode>,
+    // Drop the port of the requested host when no target port is set
+    pub drop_port: bool,
+    // Authorities of the redirection target per requested host
+    pub target_authorities: Vec<(HostPattern, String)>,
 }
 
 impl RedirectScheme {
@@ -85,10 +266,116 @@ impl RedirectScheme {
     }
 }
 
+impl RedirectScheme {
+    // Service applying the configuration to requests before passing them to the given service
+    pub(crate) fn service<S>(&self, service: Rc<RefCell<S>>) -> RedirectSchemeService<S> {
+        RedirectSchemeService {
+            service,
+            disable: self.disable,
+            https_to_http: self.https_to_http,
+            temporary: self.temporary,
+            replacements: self.replacements.clone(),
+            trusted_proxies: self.trusted_proxies.clone(),
+            prefer_x_forwarded: self.prefer_x_forwarded,
+            scheme_header: self.scheme_header.clone(),
+            cf_visitor: self.cf_visitor,
+            https_header: self.https_header.clone(),
+            unknown_scheme_policy: self.unknown_scheme_policy,
+            scheme_sources: self.scheme_sources.clone(),
+            h2c_policy: self.h2c_policy,
+            websocket_policy: self.websocket_policy,
+            forwarded_proto_policy: self.forwarded_proto_policy,
+            forwarded_host: self.forwarded_host,
+            scheme_resolver: self.scheme_resolver.clone(),
+            async_scheme_resolver: self.async_scheme_resolver.clone(),
+            upgrade_insecure_only: self.upgrade_insecure_only,
+            upgrade_insecure_status: self.upgrade_insecure_status,
+            insecure_response: self.insecure_response,
+            http_port: self.http_port,
+            https_port: self.https_port,
+            host_source: self.host_source,
+            preserve_method: self.preserve_method,
+            status_code: self.status_code,
+            unsafe_methods_status: self.unsafe_methods_status,
+            reject: self.reject.clone(),
+            upgrade_required: self.upgrade_required,
+            redirect_body: self.redirect_body,
+            json_serializer: self.json_serializer.clone(),
+            redirect_headers: self.redirect_headers.clone(),
+            cache_control: self.cache_control.clone(),
+            hsts_max_age: self.hsts_max_age,
+            hsts_include_subdomains: self.hsts_include_subdomains,
+            hsts_preload: self.hsts_preload,
+            refresh_header: self.refresh_header,
+            meta_refresh: self.meta_refresh,
+            canonical_link: self.canonical_link,
+            canonical_link_pass_through: self.canonical_link_pass_through,
+            on_redirect: self.on_redirect.clone(),
+            body_limit: self.body_limit,
+            responder: self.responder.clone(),
+            redirect_methods: self.redirect_methods.clone(),
+            diagnostic_header: self.diagnostic_header,
+            path_statuses: self.path_statuses.clone(),
+            ignore_paths: PathSet::new(&self.ignore_paths, &self.redirect_paths),
+            #[cfg(feature = "regex")]
+            ignore_path_regex: self.ignore_path_regex.clone(),
+            only_paths: self.only_paths.clone(),
+            ignore_extensions: self.ignore_extensions.clone(),
+            ignore_methods: self.ignore_methods.clone(),
+            bypass_header: self.bypass_header.clone(),
+            bypass_user_agents: self.bypass_user_agents.clone(),
+            only_hosts: self.only_hosts.clone(),
+            unknown_host_status: self.unknown_host_status,
+            ignore_hosts: self.ignore_hosts.clone(),
+            host_policies: self
+                .host_policies
+                .iter()
+                .map(|(host, policy)| (host.clone(), policy.service(Rc::new(RefCell::new(())))))
+                .collect(),
+            bypass_cidrs: self.bypass_cidrs.clone(),
+            skip_loopback: self.skip_loopback,
+            bypass_query_param: self.bypass_query_param.clone(),
+            bypass_cookie: self.bypass_cookie.clone(),
+            predicates: self.predicates.clone(),
+            force_predicates: self.force_predicates.clone(),
+            navigations_only: self.navigations_only,
+            canonical_host: self.canonical_host.clone(),
+            www_policy: self.www_policy,
+            host_rewrites: self.host_rewrites.clone(),
+            strip_query: self.strip_query,
+            query_params: self.query_params.clone(),
+            path_rewrites: self.path_rewrites.clone(),
+            trailing_slash: self.trailing_slash,
+            raw_path: self.raw_path,
+            lowercase_host: self.lowercase_host,
+            mixed_case_host_status: self.mixed_case_host_status,
+            #[cfg(feature = "idna")]
+            punycode_host: self.punycode_host,
+            location_encoding: self.location_encoding,
+            scoped_replacements: self.scoped_replacements.clone(),
+            location_template: self.location_template.clone(),
+            map_url: self.map_url.clone(),
+            fallback_host: self.fallback_host.clone(),
+            missing_host_status: self.missing_host_status,
+            userinfo_status: self.userinfo_status,
+            drop_port: self.drop_port,
+            target_authorities: self.target_authorities.clone(),
+            resolved: self.async_scheme_resolver.as_ref().map(|_| {
+                let resolved = RedirectScheme {
+                    async_scheme_resolver: None,
+                    ..self.clone()
+                };
+                Rc::new(resolved.service(Rc::new(RefCell::new(()))))
+            }),
+        }
+    }
+}
+
 impl<S, B> Transform<S> for RedirectScheme
 where
-    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
+    B: 'static,
 {
     type Request = ServiceRequest;
     type Response = ServiceResponse<B>;
@@ -98,12 +385,6 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ok(RedirectSchemeService {
-            service,
-            disable: self.disable,
-            https_to_http: self.https_to_http,
-            temporary: self.temporary,
-            replacements: self.replacements.clone(),
-        })
+        ok(self.service(Rc::new(RefCell::new(service))))
     }
 }
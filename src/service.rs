@@ -1,62 +1,1528 @@
+use crate::hint::{
+    Assume, AsyncSchemeResolver, ForwardedProtoPolicy, HostSource, ProxyProtocolInfo, SchemeHint,
+    SchemeResolver, SchemeSource,
+};
+use crate::matcher::{HostPattern, PathPattern, PathSet};
+use crate::policy::{
+    H2cPolicy, LocationEncoding, LocationSerializer, RedirectBody, RedirectHook, RedirectPredicate,
+    ReplacementScope, UrlMapper, WebSocketPolicy, WwwPolicy,
+};
+use crate::responder::RedirectResponder;
 use actix_service::Service;
 use actix_web::{
     dev::{ServiceRequest, ServiceResponse},
-    http, Error, HttpResponse,
+    http::{
+        self,
+        header::{HeaderName, HeaderValue},
+        Method, StatusCode, Uri,
+    },
+    middleware::normalize::TrailingSlash,
+    Error, HttpMessage, HttpResponse,
 };
-use futures::future::{ok, Either, Ready};
+use futures::future::{ok, LocalBoxFuture};
+use ipnet::IpNet;
+#[cfg(feature = "regex")]
+use regex::RegexSet;
+use std::cell::RefCell;
+use std::net::{IpAddr, Ipv6Addr};
+use std::rc::Rc;
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
+
+// `Cache-Control` of permanent redirections without an explicit one, as browsers otherwise keep
+// them forever and a mistaken redirection can't be undone
+const PERMANENT_CACHE_CONTROL: &str = "max-age=86400";
 
 pub struct RedirectSchemeService<S> {
-    pub service: S,
+    pub service: Rc<RefCell<S>>,
     pub disable: bool,
     pub https_to_http: bool,
     pub temporary: bool,
     pub replacements: Vec<(String, String)>,
+    pub trusted_proxies: Vec<IpNet>,
+    pub prefer_x_forwarded: bool,
+    pub scheme_header: Option<String>,
+    pub cf_visitor: bool,
+    pub https_header: Option<String>,
+    pub unknown_scheme_policy: Assume,
+    pub scheme_sources: Vec<SchemeSource>,
+    pub h2c_policy: H2cPolicy,
+    pub websocket_policy: WebSocketPolicy,
+    pub forwarded_proto_policy: ForwardedProtoPolicy,
+    pub forwarded_host: bool,
+    pub scheme_resolver: Option<SchemeResolver>,
+    pub async_scheme_resolver: Option<AsyncSchemeResolver>,
+    pub upgrade_insecure_only: bool,
+    pub upgrade_insecure_status: Option<StatusCode>,
+    pub insecure_response: Option<StatusCode>,
+    pub http_port: Option<u16>,
+    pub https_port: Option<u16>,
+    pub host_source: HostSource,
+    pub preserve_method: bool,
+    pub status_code: Option<StatusCode>,
+    pub unsafe_methods_status: Option<StatusCode>,
+    pub reject: Option<(StatusCode, String)>,
+    pub upgrade_required: bool,
+    pub redirect_body: RedirectBody,
+    pub json_serializer: Option<LocationSerializer>,
+    pub redirect_headers: Vec<(HeaderName, HeaderValue)>,
+    pub cache_control: Option<HeaderValue>,
+    pub hsts_max_age: Option<Duration>,
+    pub hsts_include_subdomains: bool,
+    pub hsts_preload: bool,
+    pub refresh_header: bool,
+    pub meta_refresh: bool,
+    pub canonical_link: bool,
+    pub canonical_link_pass_through: bool,
+    pub on_redirect: Option<RedirectHook>,
+    pub body_limit: Option<(u64, StatusCode)>,
+    pub responder: Option<Arc<dyn RedirectResponder>>,
+    pub redirect_methods: Vec<Method>,
+    pub diagnostic_header: bool,
+    pub path_statuses: Vec<(String, StatusCode)>,
+    pub ignore_paths: PathSet,
+    #[cfg(feature = "regex")]
+    pub ignore_path_regex: Option<RegexSet>,
+    pub only_paths: Vec<PathPattern>,
+    pub ignore_extensions: Vec<String>,
+    pub ignore_methods: Vec<Method>,
+    pub bypass_header: Option<(String, Option<String>)>,
+    pub bypass_user_agents: Vec<String>,
+    pub only_hosts: Vec<HostPattern>,
+    pub unknown_host_status: Option<StatusCode>,
+    pub ignore_hosts: Vec<HostPattern>,
+    pub host_policies: Vec<(HostPattern, RedirectSchemeService<()>)>,
+    pub bypass_cidrs: Vec<IpNet>,
+    pub skip_loopback: bool,
+    pub bypass_query_param: Option<(String, Option<String>)>,
+    pub bypass_cookie: Option<String>,
+    pub predicates: Vec<RedirectPredicate>,
+    pub force_predicates: Vec<RedirectPredicate>,
+    pub navigations_only: bool,
+    pub canonical_host: Option<String>,
+    pub www_policy: WwwPolicy,
+    pub host_rewrites: Vec<(String, String)>,
+    pub strip_query: bool,
+    pub query_params: Vec<(String, String)>,
+    pub path_rewrites: Vec<(String, String)>,
+    pub trailing_slash: Option<TrailingSlash>,
+    pub raw_path: bool,
+    pub lowercase_host: bool,
+    pub mixed_case_host_status: Option<StatusCode>,
+    #[cfg(feature = "idna")]
+    pub punycode_host: bool,
+    pub location_encoding: LocationEncoding,
+    pub scoped_replacements: Vec<(ReplacementScope, String, String)>,
+    pub location_template: Option<String>,
+    pub map_url: Option<UrlMapper>,
+    pub fallback_host: Option<String>,
+    pub missing_host_status: Option<StatusCode>,
+    pub userinfo_status: Option<StatusCode>,
+    pub drop_port: bool,
+    pub target_authorities: Vec<(HostPattern, String)>,
+    // Configuration without the asynchronous resolver, building responses once it resolved
+    pub resolved: Option<Rc<RedirectSchemeService<()>>>,
 }
 
-type ReadyResult<R, E> = Ready<Result<R, E>>;
+impl<S> RedirectSchemeService<S> {
+    // Whether forwarded headers of this request may be honored
+    fn is_trusted(&self, req: &ServiceRequest) -> bool {
+        if self.trusted_proxies.is_empty() {
+            return true;
+        }
+        match req.peer_addr() {
+            Some(addr) => self
+                .trusted_proxies
+                .iter()
+                .any(|net| net.contains(&addr.ip())),
+            None => false,
+        }
+    }
+
+    // Scheme of the request as seen by the client, `None` to pass the request through
+    fn scheme(&self, req: &ServiceRequest) -> Option<String> {
+        match self.detect_scheme(req) {
+            Some(scheme) => Some(scheme),
+            None => match self.unknown_scheme_policy {
+                Assume::Http => Some("http".to_owned()),
+                Assume::Https => Some("https".to_owned()),
+                Assume::PassThrough => None,
+            },
+        }
+    }
 
-impl<S, B> Service for RedirectSchemeService<S>
+    // Scheme reported by the request or its connection, `None` when unknown
+    fn detect_scheme(&self, req: &ServiceRequest) -> Option<String> {
+        if let Some(resolver) = &self.scheme_resolver {
+            if let Some(hint) = resolver(req) {
+                return Some(hint.as_str().to_owned());
+            }
+        }
+        if !self.scheme_sources.is_empty() {
+            return self
+                .scheme_sources
+                .iter()
+                .find_map(|source| self.source_scheme(*source, req));
+        }
+        let forwarded = if self.prefer_x_forwarded {
+            [SchemeSource::XForwardedProto, SchemeSource::ForwardedHeader]
+        } else {
+            [SchemeSource::ForwardedHeader, SchemeSource::XForwardedProto]
+        };
+        [SchemeSource::Extension, SchemeSource::CustomHeader]
+            .iter()
+            .chain(forwarded.iter())
+            .find_map(|source| self.source_scheme(*source, req))
+            .or_else(|| connection_scheme(req).map(|scheme| scheme.to_owned()))
+    }
+
+    // Scheme reported by a single source
+    fn source_scheme(&self, source: SchemeSource, req: &ServiceRequest) -> Option<String> {
+        match source {
+            SchemeSource::Extension => {
+                let extensions = req.extensions();
+                match extensions.get::<SchemeHint>() {
+                    Some(hint) => Some(hint.as_str().to_owned()),
+                    None => extensions
+                        .get::<ProxyProtocolInfo>()
+                        .and_then(|info| info.scheme())
+                        .map(|scheme| scheme.to_owned()),
+                }
+            }
+            SchemeSource::CustomHeader if self.is_trusted(req) => self.custom_header_scheme(req),
+            SchemeSource::ForwardedHeader if self.is_trusted(req) => {
+                forwarded_param(req, "proto").map(|scheme| scheme.to_lowercase())
+            }
+            SchemeSource::XForwardedProto if self.is_trusted(req) => {
+                header_value(req, "x-forwarded-proto")
+                    .and_then(|value| self.forwarded_proto(&value))
+            }
+            SchemeSource::TlsListener => Some(listener_scheme(req).to_owned()),
+            SchemeSource::ConnectionInfo if self.is_trusted(req) => {
+                Some(req.connection_info().scheme().to_owned())
+            }
+            SchemeSource::ConnectionInfo => connection_scheme(req).map(|scheme| scheme.to_owned()),
+            _ => None,
+        }
+    }
+
+    // Scheme from an `X-Forwarded-Proto` value according to the configured policy
+    fn forwarded_proto(&self, value: &str) -> Option<String> {
+        let mut schemes = value
+            .split(',')
+            .map(|scheme| scheme.trim().to_lowercase())
+            .filter(|scheme| !scheme.is_empty());
+        match self.forwarded_proto_policy {
+            ForwardedProtoPolicy::First => schemes.next(),
+            ForwardedProtoPolicy::Last => schemes.next_back(),
+            ForwardedProtoPolicy::RequireAllHttps => {
+                let schemes: Vec<String> = schemes.collect();
+                if schemes.is_empty() {
+                    None
+                } else if schemes.iter().all(|scheme| scheme == "https") {
+                    Some("https".to_owned())
+                } else {
+                    Some("http".to_owned())
+                }
+            }
+        }
+    }
+
+    // Scheme reported by custom proxy headers
+    fn custom_header_scheme(&self, req: &ServiceRequest) -> Option<String> {
+        if self.cf_visitor {
+            if let Some(scheme) =
+                header_value(req, "cf-visitor").and_then(|v| cf_visitor_scheme(&v))
+            {
+                return Some(scheme);
+            }
+        }
+        if let Some(name) = &self.https_header {
+            if req.headers().contains_key(name.as_str()) {
+                return Some("https".to_owned());
+            }
+        }
+        match &self.scheme_header {
+            Some(name) => header_value(req, name).map(|scheme| scheme.to_lowercase()),
+            None => None,
+        }
+    }
+
+    // Whether the request is passed through regardless of its scheme
+    fn bypass(&self, req: &ServiceRequest) -> bool {
+        if self.disable {
+            return true;
+        }
+        if self.force_predicates.iter().any(|predicate| predicate(req)) {
+            return false;
+        }
+        self.is_ignored_path(req.path())
+            || (self.websocket_policy == WebSocketPolicy::PassThrough && is_websocket(req))
+            || (self.h2c_policy == H2cPolicy::PassThrough && self.is_h2c(req))
+            || (self.insecure_response.is_none() && self.is_not_upgradable(req))
+            || self.is_ignored_method(req.method())
+            || (self.navigations_only && !is_navigation(req))
+            || self.has_bypass_header(req)
+            || self.has_bypass_query_param(req)
+            || self.has_bypass_cookie(req)
+            || self.has_bypass_user_agent(req)
+            || self.has_bypass_client(req)
+            || (self.unknown_host_status.is_none() && self.is_unknown_host(req))
+            || self.is_ignored_host(req)
+            || !self.predicates.iter().all(|predicate| predicate(req))
+    }
+
+    // Whether the request is for a host never redirected
+    fn is_ignored_host(&self, req: &ServiceRequest) -> bool {
+        if self.ignore_hosts.is_empty() {
+            return false;
+        }
+        let host = self.host(req);
+        let (name, _) = split_host_port(&host);
+        self.ignore_hosts
+            .iter()
+            .any(|ignored| ignored.matches(name))
+    }
+
+    // Whether the request is for a host not redirected
+    fn is_unknown_host(&self, req: &ServiceRequest) -> bool {
+        if self.only_hosts.is_empty() {
+            return false;
+        }
+        let host = self.host(req);
+        let (name, _) = split_host_port(&host);
+        !self.only_hosts.iter().any(|allowed| allowed.matches(name))
+    }
+
+    // Whether the request comes from a user agent which is never redirected
+    fn has_bypass_user_agent(&self, req: &ServiceRequest) -> bool {
+        if self.bypass_user_agents.is_empty() {
+            return false;
+        }
+        match header_value(req, "user-agent") {
+            Some(agent) => {
+                let agent = agent.to_lowercase();
+                self.bypass_user_agents
+                    .iter()
+                    .any(|bypass| agent.contains(bypass.as_str()))
+            }
+            None => false,
+        }
+    }
+
+    // Whether the request comes from a loopback client or a network which is never redirected
+    fn has_bypass_client(&self, req: &ServiceRequest) -> bool {
+        if self.bypass_cidrs.is_empty() && !self.skip_loopback {
+            return false;
+        }
+        match self.client_ip(req) {
+            Some(ip) => {
+                (self.skip_loopback && ip.is_loopback())
+                    || self.bypass_cidrs.iter().any(|net| net.contains(&ip))
+            }
+            None => false,
+        }
+    }
+
+    // Address of the client, the last untrusted `X-Forwarded-For` hop behind trusted proxies
+    fn client_ip(&self, req: &ServiceRequest) -> Option<IpAddr> {
+        let peer = req.peer_addr()?.ip();
+        let is_proxy = |ip: &IpAddr| self.trusted_proxies.iter().any(|net| net.contains(ip));
+        if !is_proxy(&peer) {
+            return Some(peer);
+        }
+        let mut client = peer;
+        if let Some(forwarded) = header_value(req, "x-forwarded-for") {
+            for hop in forwarded.rsplit(',') {
+                match hop.trim().parse::<IpAddr>() {
+                    Ok(ip) => {
+                        client = ip;
+                        if !is_proxy(&ip) {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+        Some(client)
+    }
+
+    // Whether the request carries the bypass header
+    fn has_bypass_header(&self, req: &ServiceRequest) -> bool {
+        match &self.bypass_header {
+            Some((name, secret)) => match (req.headers().get(name.as_str()), secret) {
+                (Some(value), Some(secret)) => secure_eq(value.as_bytes(), secret.as_bytes()),
+                (Some(_), None) => true,
+                (None, _) => false,
+            },
+            None => false,
+        }
+    }
+
+    // Whether the query string carries the bypass parameter
+    fn has_bypass_query_param(&self, req: &ServiceRequest) -> bool {
+        match &self.bypass_query_param {
+            Some((name, secret)) => req.query_string().split('&').any(|pair| {
+                let mut parts = pair.splitn(2, '=');
+                if parts.next() != Some(name.as_str()) {
+                    return false;
+                }
+                match secret {
+                    Some(secret) => {
+                        secure_eq(parts.next().unwrap_or("").as_bytes(), secret.as_bytes())
+                    }
+                    None => true,
+                }
+            }),
+            None => false,
+        }
+    }
+
+    // Whether the request carries the bypass cookie
+    fn has_bypass_cookie(&self, req: &ServiceRequest) -> bool {
+        let name = match &self.bypass_cookie {
+            Some(name) => name,
+            None => return false,
+        };
+        req.headers()
+            .get_all(http::header::COOKIE)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .any(|pair| pair.trim().split('=').next() == Some(name.as_str()))
+    }
+
+    // Whether requests with the method are never redirected
+    fn is_ignored_method(&self, method: &Method) -> bool {
+        self.ignore_methods.contains(method)
+            || (!self.redirect_methods.is_empty() && !self.redirect_methods.contains(method))
+    }
+
+    // Whether the request lacks `Upgrade-Insecure-Requests` although it is required
+    fn is_not_upgradable(&self, req: &ServiceRequest) -> bool {
+        self.upgrade_insecure_only && !self.https_to_http && !has_upgrade_insecure(req)
+    }
+
+    // Whether the path is never redirected
+    fn is_ignored_path(&self, path: &str) -> bool {
+        if let Some(ext) = extension(path) {
+            if self
+                .ignore_extensions
+                .iter()
+                .any(|ignored| ignored.eq_ignore_ascii_case(ext))
+            {
+                return true;
+            }
+        }
+        if !self.only_paths.is_empty()
+            && !self.only_paths.iter().any(|pattern| pattern.matches(path))
+        {
+            return true;
+        }
+        #[cfg(feature = "regex")]
+        {
+            if let Some(regex) = &self.ignore_path_regex {
+                if regex.is_match(path) {
+                    return true;
+                }
+            }
+        }
+        self.ignore_paths.matches(path)
+    }
+
+    // Whether the request is an HTTP/2 cleartext request to be upgraded to HTTPS
+    fn is_h2c(&self, req: &ServiceRequest) -> bool {
+        !self.https_to_http && req.version() == http::Version::HTTP_2 && !req.app_config().secure()
+    }
+
+    // Response to a request which has to be redirected
+    fn response(&self, req: &ServiceRequest) -> HttpResponse {
+        let mut response = self.build_response(req);
+        let headers = response.headers_mut();
+        for (name, value) in self.redirect_headers.iter() {
+            headers.append(name.clone(), value.clone());
+        }
+        if self.diagnostic_header {
+            let value = if self.https_to_http {
+                "redirect-scheme (reason=https_to_http)"
+            } else {
+                "redirect-scheme (reason=http_to_https)"
+            };
+            headers.insert(
+                HeaderName::from_static("x-redirected-by"),
+                HeaderValue::from_static(value),
+            );
+        }
+        if let Some(hook) = &self.on_redirect {
+            hook(req, &mut response);
+        }
+        response
+    }
+
+    // Response to a request which has to be redirected, without custom headers
+    fn build_response(&self, req: &ServiceRequest) -> HttpResponse {
+        if let H2cPolicy::Respond(status) = self.h2c_policy {
+            if self.is_h2c(req) {
+                return HttpResponse::build(status).finish();
+            }
+        }
+        if let Some(status) = self.unknown_host_status {
+            if self.is_unknown_host(req) {
+                return HttpResponse::build(status).finish();
+            }
+        }
+        if let Some(status) = self.missing_host_status {
+            if !has_host(req) {
+                return HttpResponse::build(status).finish();
+            }
+        }
+        if let Some(status) = self.userinfo_status {
+            if self.host(req).contains('@') {
+                return HttpResponse::build(status).finish();
+            }
+        }
+        if let Some(status) = self.mixed_case_host_status {
+            if self.host(req).bytes().any(|byte| byte.is_ascii_uppercase()) {
+                return HttpResponse::build(status).finish();
+            }
+        }
+        if let Some(status) = self.insecure_response {
+            if self.is_not_upgradable(req) {
+                return HttpResponse::build(status).finish();
+            }
+        }
+        if let Some((status, body)) = &self.reject {
+            return HttpResponse::build(*status)
+                .content_type("text/plain; charset=utf-8")
+                .body(body.clone());
+        }
+        if self.upgrade_required && !self.https_to_http {
+            return HttpResponse::build(StatusCode::UPGRADE_REQUIRED)
+                .header(http::header::UPGRADE, "TLS/1.3")
+                .header(http::header::CONNECTION, "Upgrade")
+                .finish();
+        }
+        let location = match self.location(req) {
+            Some(location) => location,
+            None => return HttpResponse::BadRequest().finish(),
+        };
+        if let Some((limit, status)) = self.body_limit {
+            if content_length(req).is_some_and(|length| length > limit) {
+                return HttpResponse::build(status)
+                    .content_type("text/plain; charset=utf-8")
+                    .body(format!(
+                        "Request body too large to be redirected, resend it to {}\n",
+                        location
+                    ));
+            }
+        }
+        match &self.responder {
+            Some(responder) => responder.respond(req, &location),
+            None => self.redirect(req, &location),
+        }
+    }
+
+    // Redirection to the given location
+    fn redirect(&self, req: &ServiceRequest, location: &str) -> HttpResponse {
+        let status = self.status(req);
+        let mut response = HttpResponse::build(status);
+        response.header(http::header::LOCATION, location);
+        match &self.cache_control {
+            Some(value) => {
+                response.header(http::header::CACHE_CONTROL, value.clone());
+            }
+            None if status == StatusCode::MOVED_PERMANENTLY
+                || status == StatusCode::PERMANENT_REDIRECT =>
+            {
+                response.header(http::header::CACHE_CONTROL, PERMANENT_CACHE_CONTROL);
+            }
+            None => {}
+        }
+        let vary = self.vary();
+        if !vary.is_empty() {
+            response.header(http::header::VARY, vary.join(", "));
+        }
+        if self.canonical_link {
+            response.header(http::header::LINK, canonical_link(location));
+        }
+        if self.refresh_header {
+            response.header("refresh", format!("0; url={}", location));
+        }
+        let body = match self.redirect_body {
+            RedirectBody::Negotiate => negotiate_body(req),
+            RedirectBody::Empty if self.meta_refresh => RedirectBody::Html,
+            body => body,
+        };
+        match body {
+            RedirectBody::Html => {
+                let location = escape_html(location);
+                let meta = if self.meta_refresh {
+                    format!(
+                        "<meta http-equiv=\"refresh\" content=\"0; url={}\">",
+                        location
+                    )
+                } else {
+                    String::new()
+                };
+                response
+                    .content_type("text/html; charset=utf-8")
+                    .body(format!(
+                        "<!DOCTYPE html>\n<html><head><title>Redirect</title>{1}</head>\
+                     <body><p>Moved to <a href=\"{0}\">{0}</a>.</p></body></html>\n",
+                        location, meta
+                    ))
+            }
+            RedirectBody::Text => response
+                .content_type("text/plain; charset=utf-8")
+                .body(format!("Moved to {}\n", location)),
+            RedirectBody::Json | RedirectBody::Negotiate => {
+                let json = match &self.json_serializer {
+                    Some(serializer) => serializer(location),
+                    None => format!("{{\"location\":\"{}\"}}", escape_json(location)),
+                };
+                response.content_type("application/json").body(json)
+            }
+            RedirectBody::Empty => response.finish(),
+        }
+    }
+
+    // Request headers which may influence the redirection
+    fn vary(&self) -> Vec<&str> {
+        let mut vary = Vec::new();
+        let sources = if self.scheme_sources.is_empty() {
+            &[
+                SchemeSource::CustomHeader,
+                SchemeSource::ForwardedHeader,
+                SchemeSource::XForwardedProto,
+            ][..]
+        } else {
+            &self.scheme_sources[..]
+        };
+        for source in sources {
+            match source {
+                SchemeSource::CustomHeader => {
+                    if self.cf_visitor {
+                        vary.push("CF-Visitor");
+                    }
+                    if let Some(name) = &self.https_header {
+                        vary.push(name.as_str());
+                    }
+                    if let Some(name) = &self.scheme_header {
+                        vary.push(name.as_str());
+                    }
+                }
+                SchemeSource::ForwardedHeader => vary.push("Forwarded"),
+                SchemeSource::XForwardedProto => vary.push("X-Forwarded-Proto"),
+                SchemeSource::ConnectionInfo => {
+                    vary.push("Forwarded");
+                    vary.push("X-Forwarded-Proto");
+                }
+                SchemeSource::TlsListener | SchemeSource::Extension => {}
+            }
+        }
+        if self.host_source == HostSource::ConnectionInfo {
+            vary.push("X-Forwarded-Host");
+        }
+        if self.upgrade_insecure_only || self.upgrade_insecure_status.is_some() {
+            vary.push("Upgrade-Insecure-Requests");
+        }
+        if self.websocket_policy == WebSocketPolicy::RedirectWebSocket {
+            vary.push("Upgrade");
+        }
+        if self.redirect_body == RedirectBody::Negotiate {
+            vary.push("Accept");
+        }
+        if self.navigations_only {
+            vary.push("Sec-Fetch-Dest");
+        }
+        if let Some((name, _)) = &self.bypass_header {
+            vary.push(name.as_str());
+        }
+        if self.bypass_cookie.is_some() {
+            vary.push("Cookie");
+        }
+        if !self.bypass_user_agents.is_empty() {
+            vary.push("User-Agent");
+        }
+        let mut unique = Vec::with_capacity(vary.len());
+        for name in vary {
+            if !unique
+                .iter()
+                .any(|seen: &&str| seen.eq_ignore_ascii_case(name))
+            {
+                unique.push(name);
+            }
+        }
+        unique
+    }
+
+    // Headers added to the response of a request with the given scheme which is passed through
+    fn pass_headers(
+        &self,
+        req: &ServiceRequest,
+        scheme: Option<&str>,
+    ) -> Vec<(HeaderName, HeaderValue)> {
+        let mut headers = Vec::new();
+        if let Some(value) = self.hsts(scheme) {
+            headers.push((http::header::STRICT_TRANSPORT_SECURITY, value));
+        }
+        if self.canonical_link_pass_through {
+            if let Some(location) = self.location(req) {
+                if let Ok(value) = HeaderValue::from_str(&canonical_link(&location)) {
+                    headers.push((http::header::LINK, value));
+                }
+            }
+        }
+        headers
+    }
+
+    // Headers added to the response of a request passed through regardless of its scheme
+    fn bypass_headers(&self, req: &ServiceRequest) -> Vec<(HeaderName, HeaderValue)> {
+        if self.hsts_max_age.is_none() {
+            return Vec::new();
+        }
+        self.hsts(self.scheme(req).as_deref())
+            .map(|value| (http::header::STRICT_TRANSPORT_SECURITY, value))
+            .into_iter()
+            .collect()
+    }
+
+    // HSTS header for a request with the given scheme which is passed through
+    fn hsts(&self, scheme: Option<&str>) -> Option<HeaderValue> {
+        match self.hsts_max_age {
+            Some(max_age) if !self.https_to_http && scheme == Some("https") => {
+                let mut value = format!("max-age={}", max_age.as_secs());
+                if self.hsts_include_subdomains {
+                    value.push_str("; includeSubDomains");
+                }
+                if self.hsts_preload {
+                    value.push_str("; preload");
+                }
+                HeaderValue::from_str(&value).ok()
+            }
+            _ => None,
+        }
+    }
+
+    // Status code of the redirection
+    fn status(&self, req: &ServiceRequest) -> StatusCode {
+        if let Some(status) = self.upgrade_insecure_status {
+            if !self.https_to_http && has_upgrade_insecure(req) {
+                return status;
+            }
+        }
+        let path = req.path();
+        if let Some((_, status)) = self
+            .path_statuses
+            .iter()
+            .filter(|(prefix, _)| has_path_prefix(path, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+        {
+            return *status;
+        }
+        if let Some(status) = self.unsafe_methods_status {
+            if !is_safe_method(req.method()) {
+                return status;
+            }
+        }
+        if let Some(status) = self.status_code {
+            status
+        } else if self.temporary {
+            StatusCode::TEMPORARY_REDIRECT
+        } else if self.preserve_method {
+            StatusCode::PERMANENT_REDIRECT
+        } else {
+            StatusCode::MOVED_PERMANENTLY
+        }
+    }
+
+    // Scheme of the redirection target
+    fn target_scheme(&self, req: &ServiceRequest) -> &'static str {
+        let websocket =
+            self.websocket_policy == WebSocketPolicy::RedirectWebSocket && is_websocket(req);
+        match (self.https_to_http, websocket) {
+            (true, false) => "http",
+            (false, false) => "https",
+            (true, true) => "ws",
+            (false, true) => "wss",
+        }
+    }
+
+    // Target of the redirection
+    //
+    // `None` if the parts of the target don't form a valid URI.
+    fn location(&self, req: &ServiceRequest) -> Option<String> {
+        let url = match &self.location_template {
+            Some(template) => self.fill_template(template, req)?,
+            None => self.build_location(req)?,
+        };
+        Some(match &self.map_url {
+            Some(map_url) => map_url(url, req),
+            None => url,
+        })
+    }
+
+    // Target of the redirection built from its parts
+    fn build_location(&self, req: &ServiceRequest) -> Option<String> {
+        let uri = Uri::builder()
+            .scheme(self.target_scheme(req))
+            .authority(
+                self.replace(ReplacementScope::Host, self.target_host(req))
+                    .as_str(),
+            )
+            .path_and_query(self.target_path(req)?.as_str())
+            .build()
+            .ok()?;
+        let mut url = uri.to_string();
+        for (s1, s2) in self.replacements.iter() {
+            url = url.replace(s1, s2);
+        }
+        Some(self.replace(ReplacementScope::Whole, url))
+    }
+
+    // Target of the redirection from the template
+    fn fill_template(&self, template: &str, req: &ServiceRequest) -> Option<String> {
+        let host = self.replace(ReplacementScope::Host, self.target_host(req));
+        let (name, port) = split_host_port(&host);
+        let target = self.target_path(req)?;
+        let (path, query) = match target.find('?') {
+            Some(i) => target.split_at(i),
+            None => (target.as_str(), ""),
+        };
+        let url = template
+            .replace("{scheme}", self.target_scheme(req))
+            .replace("{host}", name)
+            .replace("{port}", port.unwrap_or(""))
+            .replace("{http_port}", &self.http_port.unwrap_or(80).to_string())
+            .replace("{https_port}", &self.https_port.unwrap_or(443).to_string())
+            .replace("{path}", path)
+            .replace("{query}", query);
+        url.parse::<Uri>().ok()?;
+        Some(self.replace(ReplacementScope::Whole, url))
+    }
+
+    // Text of a location component with the replacements of its scope applied
+    fn replace(&self, scope: ReplacementScope, mut text: String) -> String {
+        for (_, from, to) in self
+            .scoped_replacements
+            .iter()
+            .filter(|(replacement_scope, _, _)| *replacement_scope == scope)
+        {
+            text = text.replace(from.as_str(), to);
+        }
+        text
+    }
+
+    // Path and query of the redirection target
+    //
+    // `None` if it contains invalid characters which are rejected.
+    fn target_path(&self, req: &ServiceRequest) -> Option<String> {
+        let uri = req.uri();
+        let path = self.replace(ReplacementScope::Path, self.path(uri.path()));
+        let mut query = match uri.query() {
+            Some(query) if !self.strip_query => {
+                self.replace(ReplacementScope::Query, query.to_owned())
+            }
+            _ => String::new(),
+        };
+        for (name, value) in self.query_params.iter() {
+            if !query.is_empty() && !query.ends_with('&') {
+                query.push('&');
+            }
+            query.push_str(&encode_query(name));
+            query.push('=');
+            query.push_str(&encode_query(value));
+        }
+        let target = if query.is_empty() && (self.strip_query || uri.query().is_none()) {
+            path
+        } else {
+            format!("{}?{}", path, query)
+        };
+        if is_uri_encoded(&target) {
+            Some(target)
+        } else if self.location_encoding == LocationEncoding::Encode {
+            Some(encode_uri(&target))
+        } else {
+            None
+        }
+    }
+
+    // Path of the redirection target
+    fn path(&self, path: &str) -> String {
+        let path = if self.raw_path || !path.starts_with('/') {
+            self.rewrite_path(path)
+        } else {
+            self.rewrite_path(&normalize_path(path))
+        };
+        match self.trailing_slash {
+            Some(TrailingSlash::Trim) if path.len() > 1 => {
+                let trimmed = path.trim_end_matches('/');
+                if trimmed.is_empty() {
+                    "/".to_owned()
+                } else {
+                    trimmed.to_owned()
+                }
+            }
+            Some(TrailingSlash::Always) if !path.ends_with('/') => format!("{}/", path),
+            Some(TrailingSlash::MergeOnly) => merge_slashes(&path),
+            _ => path,
+        }
+    }
+
+    // Path with the first matching prefix rewrite applied
+    fn rewrite_path(&self, path: &str) -> String {
+        for (from, to) in self.path_rewrites.iter() {
+            let from = from.trim_end_matches('/');
+            if let Some(rest) = path.strip_prefix(from) {
+                if rest.is_empty() || rest.starts_with('/') {
+                    let path = format!("{}{}", to.trim_end_matches('/'), rest);
+                    return if path.is_empty() {
+                        "/".to_owned()
+                    } else {
+                        path
+                    };
+                }
+            }
+        }
+        path.to_owned()
+    }
+
+    // Host of the redirection target
+    fn target_host(&self, req: &ServiceRequest) -> String {
+        let host = match (&self.canonical_host, &self.fallback_host) {
+            (Some(host), _) => host.clone(),
+            (None, Some(host)) if !has_host(req) => host.clone(),
+            (None, _) => self.host(req),
+        };
+        let host = strip_userinfo(&host);
+        let (name, request_port) = split_host_port(host);
+        if let Some((_, authority)) = self
+            .target_authorities
+            .iter()
+            .find(|(pattern, _)| pattern.matches(name))
+        {
+            return authority.clone();
+        }
+        let port = if self.https_to_http {
+            self.http_port
+        } else {
+            self.https_port
+        };
+        let name = self.host_name(name);
+        match port {
+            Some(port) => {
+                let default = if self.https_to_http { 80 } else { 443 };
+                if port == default {
+                    name
+                } else {
+                    format!("{}:{}", name, port)
+                }
+            }
+            // The requested port belongs to the listener of the other scheme
+            None if self.http_port.is_some() || self.https_port.is_some() => name,
+            None => match request_port {
+                Some(port) if !self.drop_port => format!("{}:{}", name, port),
+                _ => name,
+            },
+        }
+    }
+
+    // Host name of the redirection target, without port
+    fn host_name(&self, name: &str) -> String {
+        let name = self
+            .host_rewrites
+            .iter()
+            .find(|(from, _)| from.eq_ignore_ascii_case(name))
+            .map_or(name, |(_, to)| to.as_str());
+        if name.parse::<Ipv6Addr>().is_ok() {
+            return format!("[{}]", name);
+        }
+        let is_domain =
+            name.contains('.') && name.parse::<IpAddr>().is_err() && !name.starts_with('[');
+        let name = match self.www_policy {
+            WwwPolicy::Add if is_domain && !has_www(name) => format!("www.{}", name),
+            WwwPolicy::Remove if has_www(name) => name[4..].to_owned(),
+            _ => name.to_owned(),
+        };
+        #[cfg(feature = "idna")]
+        let name = if self.punycode_host && !name.is_ascii() {
+            idna::domain_to_ascii(&name).unwrap_or(name)
+        } else {
+            name
+        };
+        if self.lowercase_host {
+            name.to_lowercase()
+        } else {
+            name
+        }
+    }
+
+    // Host of the request as seen by the client
+    fn host(&self, req: &ServiceRequest) -> String {
+        if self.host_source == HostSource::HostHeader || !self.is_trusted(req) {
+            return connection_host(req);
+        }
+        if self.forwarded_host || self.prefer_x_forwarded {
+            if let Some(host) = header_value(req, "x-forwarded-host")
+                .and_then(|value| value.split(',').next().map(|host| host.trim().to_owned()))
+            {
+                return host;
+            }
+        }
+        req.connection_info().host().to_owned()
+    }
+
+    // Redirect the request or pass it to the given service, according to the policy of its host
+    pub(crate) fn dispatch<T, B>(
+        &self,
+        service: &Rc<RefCell<T>>,
+        req: ServiceRequest,
+    ) -> LocalBoxFuture<'static, Result<ServiceResponse<B>, Error>>
+    where
+        T: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+        T: 'static,
+        T::Future: 'static,
+        B: 'static,
+    {
+        match self.host_policy(&req) {
+            Some(policy) => policy.handle(service, req),
+            None => self.handle(service, req),
+        }
+    }
+
+    // Redirect the request or pass it to the given service
+    fn handle<T, B>(
+        &self,
+        service: &Rc<RefCell<T>>,
+        req: ServiceRequest,
+    ) -> LocalBoxFuture<'static, Result<ServiceResponse<B>, Error>>
+    where
+        T: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+        T: 'static,
+        T::Future: 'static,
+        B: 'static,
+    {
+        if self.bypass(&req) {
+            let headers = self.bypass_headers(&req);
+            return forward(service, req, headers);
+        }
+        let scheme = self.scheme(&req);
+        let (resolver, resolved) = match (&self.async_scheme_resolver, &self.resolved) {
+            (Some(resolver), Some(resolved)) => (resolver, resolved.clone()),
+            _ => {
+                return if redirects(self.https_to_http, scheme.as_deref()) {
+                    let response = self.response(&req);
+                    Box::pin(ok(respond(req, response)))
+                } else {
+                    let headers = self.pass_headers(&req, scheme.as_deref());
+                    forward(service, req, headers)
+                };
+            }
+        };
+        let hint = resolver(&req);
+        let service = service.clone();
+        Box::pin(async move {
+            let scheme = match hint.await {
+                Some(hint) => Some(hint.as_str().to_owned()),
+                None => scheme,
+            };
+            if redirects(resolved.https_to_http, scheme.as_deref()) {
+                let response = resolved.response(&req);
+                Ok(respond(req, response))
+            } else {
+                let headers = resolved.pass_headers(&req, scheme.as_deref());
+                forward(&service, req, headers).await
+            }
+        })
+    }
+
+    // Policy configured for the host of the request
+    fn host_policy(&self, req: &ServiceRequest) -> Option<&RedirectSchemeService<()>> {
+        if self.host_policies.is_empty() {
+            return None;
+        }
+        let host = self.host(req);
+        let (name, _) = split_host_port(&host);
+        self.host_policies
+            .iter()
+            .find(|(pattern, _)| pattern.matches(name))
+            .map(|(_, policy)| policy)
+    }
+}
+
+// File extension of the last segment of the path
+fn extension(path: &str) -> Option<&str> {
+    let segment = &path[path.rfind('/').map_or(0, |i| i + 1)..];
+    match segment.rfind('.') {
+        Some(i) if i > 0 => Some(&segment[i + 1..]),
+        _ => None,
+    }
+}
+
+// Whether the path is the prefix or lies below it, e.g. `/api/users` but not `/apiary` for `/api`
+fn has_path_prefix(path: &str, prefix: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/'),
+        None => false,
+    }
+}
+
+// Comparison of secrets taking the same time wherever they differ
+fn secure_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Trimmed non-empty value of a request header
+fn header_value(req: &ServiceRequest, name: &str) -> Option<String> {
+    let value = req.headers().get(name)?.to_str().ok()?.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_owned())
+    }
+}
+
+// Scheme from a Cloudflare `CF-Visitor` value like `{"scheme":"https"}`
+fn cf_visitor_scheme(value: &str) -> Option<String> {
+    let rest = &value[value.find("\"scheme\"")? + "\"scheme\"".len()..];
+    let rest = rest
+        .trim_start()
+        .strip_prefix(':')?
+        .trim_start()
+        .strip_prefix('"')?;
+    let scheme = &rest[..rest.find('"')?];
+    if scheme.is_empty() {
+        None
+    } else {
+        Some(scheme.to_lowercase())
+    }
+}
+
+// Value of a parameter from the first element of the RFC 7239 `Forwarded` header
+fn forwarded_param(req: &ServiceRequest, name: &str) -> Option<String> {
+    let header = req.headers().get(http::header::FORWARDED)?.to_str().ok()?;
+    let element = header.split(',').next()?;
+    element.split(';').find_map(|pair| {
+        let mut parts = pair.splitn(2, '=');
+        let key = parts.next()?.trim();
+        let value = parts.next()?.trim().trim_matches('"');
+        if key.eq_ignore_ascii_case(name) && !value.is_empty() {
+            Some(value.to_owned())
+        } else {
+            None
+        }
+    })
+}
+
+// Path without duplicate slashes and `.` and `..` segments
+fn normalize_path(path: &str) -> String {
+    let mut segments = Vec::new();
+    let mut trailing = false;
+    for segment in path.split('/').skip(1) {
+        trailing = true;
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => {
+                segments.push(segment);
+                trailing = false;
+            }
+        }
+    }
+    let mut normalized = String::with_capacity(path.len());
+    for segment in segments {
+        normalized.push('/');
+        normalized.push_str(segment);
+    }
+    if trailing || normalized.is_empty() {
+        normalized.push('/');
+    }
+    normalized
+}
+
+// Path with runs of slashes merged into one
+fn merge_slashes(path: &str) -> String {
+    let mut merged = String::with_capacity(path.len());
+    for c in path.chars() {
+        if c != '/' || !merged.ends_with('/') {
+            merged.push(c);
+        }
+    }
+    merged
+}
+
+// Host without userinfo like `user:pass@`
+fn strip_userinfo(host: &str) -> &str {
+    host.rsplit('@').next().unwrap_or(host)
+}
+
+// Whether the host name starts with `www.`
+fn has_www(name: &str) -> bool {
+    name.len() > 4
+        && name
+            .get(..4)
+            .is_some_and(|www| www.eq_ignore_ascii_case("www."))
+}
+
+// Split a host like `example.com:8080` or `[::1]:8080` into name and port, a bare IPv6 address
+// like `::1` has no port
+fn split_host_port(host: &str) -> (&str, Option<&str>) {
+    if host.starts_with('[') {
+        return match host.find(']') {
+            Some(i) => (&host[..=i], host[i + 1..].strip_prefix(':')),
+            None => (host, None),
+        };
+    }
+    match host.rfind(':') {
+        Some(i) if !host[..i].contains(':') => (&host[..i], Some(&host[i + 1..])),
+        _ => (host, None),
+    }
+}
+
+// Whether the request names a host in its `Host` header or absolute URI
+fn has_host(req: &ServiceRequest) -> bool {
+    header_value(req, "host").is_some() || req.uri().authority().is_some()
+}
+
+// Host of the immediate connection, ignoring any forwarded headers
+fn connection_host(req: &ServiceRequest) -> String {
+    req.headers()
+        .get(http::header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| req.uri().authority().map(|authority| authority.as_str()))
+        .unwrap_or_else(|| req.app_config().host())
+        .to_owned()
+}
+
+// Scheme of the listener the request arrived on
+fn listener_scheme(req: &ServiceRequest) -> &'static str {
+    if req.app_config().secure() {
+        "https"
+    } else {
+        "http"
+    }
+}
+
+// Scheme of the immediate connection, ignoring any forwarded headers
+fn connection_scheme(req: &ServiceRequest) -> Option<&str> {
+    if let Some(scheme) = req.uri().scheme_str() {
+        Some(scheme)
+    } else if req.app_config().secure() {
+        Some("https")
+    } else {
+        None
+    }
+}
+
+// Whether the request is a WebSocket handshake with `Connection: Upgrade` and
+// `Upgrade: websocket`
+fn is_websocket(req: &ServiceRequest) -> bool {
+    header_value(req, "upgrade").is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+        && header_value(req, "connection").is_some_and(|value| {
+            value
+                .split(',')
+                .any(|option| option.trim().eq_ignore_ascii_case("upgrade"))
+        })
+}
+
+// Whether the request is a top-level navigation or does not tell its destination
+fn is_navigation(req: &ServiceRequest) -> bool {
+    match header_value(req, "sec-fetch-dest") {
+        Some(dest) => dest.eq_ignore_ascii_case("document"),
+        None => true,
+    }
+}
+
+// Whether the request carries `Upgrade-Insecure-Requests: 1`, as sent by navigating browsers
+fn has_upgrade_insecure(req: &ServiceRequest) -> bool {
+    header_value(req, "upgrade-insecure-requests").as_deref() == Some("1")
+}
+
+// Length of the request body according to its `Content-Length` header
+fn content_length(req: &ServiceRequest) -> Option<u64> {
+    header_value(req, "content-length")?.parse().ok()
+}
+
+// Whether the method is safe, i.e. a redirection can't lose a request body
+fn is_safe_method(method: &http::Method) -> bool {
+    matches!(
+        *method,
+        http::Method::GET | http::Method::HEAD | http::Method::OPTIONS | http::Method::TRACE
+    )
+}
+
+// Whether a request with the given scheme has to be redirected
+fn redirects(https_to_http: bool, scheme: Option<&str>) -> bool {
+    match scheme {
+        Some(scheme) if https_to_http => scheme != "http",
+        Some(scheme) => scheme != "https",
+        None => false,
+    }
+}
+
+// `Link` header value naming the canonical URL
+fn canonical_link(url: &str) -> String {
+    format!("<{}>; rel=\"canonical\"", url)
+}
+
+// Text escaped for use in HTML content and attributes
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Whether the byte may appear unencoded in the path or query of a URI
+fn is_uri_byte(byte: u8) -> bool {
+    byte.is_ascii_graphic() && !b"\"<>\\^`{|}".contains(&byte)
+}
+
+// Whether the `%` at the index starts a valid percent-encoded byte
+fn is_percent_encoded(bytes: &[u8], i: usize) -> bool {
+    bytes.len() > i + 2 && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit()
+}
+
+// Whether the path and query are validly encoded
+fn is_uri_encoded(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes
+        .iter()
+        .enumerate()
+        .all(|(i, &byte)| is_uri_byte(byte) && (byte != b'%' || is_percent_encoded(bytes, i)))
+}
+
+// Path and query with invalid bytes and stray `%` percent-encoded
+fn encode_uri(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut encoded = String::with_capacity(text.len());
+    for (i, &byte) in bytes.iter().enumerate() {
+        if is_uri_byte(byte) && (byte != b'%' || is_percent_encoded(bytes, i)) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+// Text percent-encoded for use as a query parameter name or value
+fn encode_query(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+// Text escaped for use in a JSON string
+fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Body of a redirection according to the `Accept` header of the request
+fn negotiate_body(req: &ServiceRequest) -> RedirectBody {
+    let accept = header_value(req, "accept").unwrap_or_default();
+    if accept.contains("application/json") {
+        RedirectBody::Json
+    } else if accept.contains("text/html") {
+        RedirectBody::Html
+    } else {
+        RedirectBody::Empty
+    }
+}
+
+// Pass the request to the wrapped service, adding the given headers to its response unless
+// already present
+fn forward<S, B>(
+    service: &Rc<RefCell<S>>,
+    req: ServiceRequest,
+    headers: Vec<(HeaderName, HeaderValue)>,
+) -> LocalBoxFuture<'static, Result<ServiceResponse<B>, Error>>
 where
     S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
     S::Future: 'static,
+{
+    let fut = service.borrow_mut().call(req);
+    if headers.is_empty() {
+        return Box::pin(fut);
+    }
+    Box::pin(async move {
+        let mut res = fut.await?;
+        let res_headers = res.headers_mut();
+        for (name, value) in headers {
+            if !res_headers.contains_key(&name) {
+                res_headers.insert(name, value);
+            }
+        }
+        Ok(res)
+    })
+}
+
+// Response to the request with a body of any type
+fn respond<B>(req: ServiceRequest, response: HttpResponse) -> ServiceResponse<B> {
+    req.into_response(response.into_body())
+}
+
+impl<S, B> Service for RedirectSchemeService<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
 {
     type Request = ServiceRequest;
     type Response = ServiceResponse<B>;
     type Error = Error;
-    type Future = Either<S::Future, ReadyResult<Self::Response, Self::Error>>;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
 
     fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
-        self.service.poll_ready(cx)
+        self.service.borrow_mut().poll_ready(cx)
     }
 
     fn call(&mut self, req: ServiceRequest) -> Self::Future {
-        if self.disable
-            || (!self.https_to_http && req.connection_info().scheme() == "https")
-            || (self.https_to_http && req.connection_info().scheme() == "http")
-        {
-            Either::Left(self.service.call(req))
-        } else {
-            let host = req.connection_info().host().to_owned();
-            let uri = req.uri().to_owned();
-            let mut url = if self.https_to_http {
-                format!("http://{}{}", host, uri)
-            } else {
-                format!("https://{}{}", host, uri)
-            };
-            for (s1, s2) in self.replacements.iter() {
-                url = url.replace(s1, s2);
+        self.dispatch(&self.service, req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::RedirectSchemeBuilder;
+    use actix_web::test::TestRequest;
+    use std::net::SocketAddr;
+
+    fn peer(ip: &str) -> SocketAddr {
+        SocketAddr::new(ip.parse().unwrap(), 12345)
+    }
+
+    #[test]
+    fn split_host_port_handles_ipv6() {
+        assert_eq!(split_host_port("example.com"), ("example.com", None));
+        assert_eq!(
+            split_host_port("example.com:8080"),
+            ("example.com", Some("8080"))
+        );
+        assert_eq!(split_host_port("[::1]"), ("[::1]", None));
+        assert_eq!(split_host_port("[::1]:8443"), ("[::1]", Some("8443")));
+        assert_eq!(split_host_port("::1"), ("::1", None));
+        assert_eq!(split_host_port("[::1"), ("[::1", None));
+    }
+
+    #[test]
+    fn normalize_path_resolves_segments() {
+        assert_eq!(normalize_path(""), "/");
+        assert_eq!(normalize_path("/"), "/");
+        assert_eq!(normalize_path("/a//b/./c/../d"), "/a/b/d");
+        assert_eq!(normalize_path("/a/b/"), "/a/b/");
+        assert_eq!(normalize_path("/a/b/.."), "/a/");
+        assert_eq!(normalize_path("/../../etc/passwd"), "/etc/passwd");
+    }
+
+    #[test]
+    fn encode_uri_escapes_invalid_bytes() {
+        assert_eq!(encode_uri("/a b?q=<x>"), "/a%20b?q=%3Cx%3E");
+        assert_eq!(encode_uri("/100%"), "/100%25");
+        assert_eq!(encode_uri("/%41%zz"), "/%41%25zz");
+        assert_eq!(encode_uri("/café"), "/caf%C3%A9");
+        assert!(is_uri_encoded("/a%20b?q=1"));
+        assert!(!is_uri_encoded("/a b"));
+        assert!(!is_uri_encoded("/100%"));
+    }
+
+    #[test]
+    fn forwarded_param_reads_first_element() {
+        let req = TestRequest::default()
+            .header(
+                "forwarded",
+                "for=192.0.2.60;Proto=\"HTTPS\";by=203.0.113.43, for=198.51.100.17;proto=http",
+            )
+            .to_srv_request();
+        assert_eq!(forwarded_param(&req, "proto"), Some("HTTPS".to_owned()));
+        assert_eq!(forwarded_param(&req, "for"), Some("192.0.2.60".to_owned()));
+        assert_eq!(forwarded_param(&req, "host"), None);
+
+        let req = TestRequest::default()
+            .header("forwarded", "for=192.0.2.60;proto=")
+            .to_srv_request();
+        assert_eq!(forwarded_param(&req, "proto"), None);
+        assert_eq!(
+            forwarded_param(&TestRequest::default().to_srv_request(), "proto"),
+            None
+        );
+    }
+
+    #[test]
+    fn cf_visitor_scheme_parses_json() {
+        assert_eq!(
+            cf_visitor_scheme("{\"scheme\":\"https\"}"),
+            Some("https".to_owned())
+        );
+        assert_eq!(
+            cf_visitor_scheme("{ \"scheme\" : \"HTTP\" }"),
+            Some("http".to_owned())
+        );
+        assert_eq!(cf_visitor_scheme("{\"scheme\":\"\"}"), None);
+        assert_eq!(cf_visitor_scheme("{\"scheme\":https}"), None);
+        assert_eq!(cf_visitor_scheme("https"), None);
+    }
+
+    #[test]
+    fn client_ip_walks_forwarded_for() {
+        let service = RedirectSchemeBuilder::new()
+            .trust_forwarded_from(&["10.0.0.0/8"])
+            .build()
+            .service(Rc::new(RefCell::new(())));
+        let client = |peer_ip: &str, forwarded: Option<&str>| {
+            let mut req = TestRequest::default().peer_addr(peer(peer_ip));
+            if let Some(forwarded) = forwarded {
+                req = req.header("x-forwarded-for", forwarded);
             }
-            Either::Right(ok(req.into_response(
-                if self.temporary {
-                    HttpResponse::TemporaryRedirect()
-                } else {
-                    HttpResponse::MovedPermanently()
-                }
-                .header(http::header::LOCATION, url)
-                .finish()
-                .into_body(),
-            )))
-        }
+            service.client_ip(&req.to_srv_request())
+        };
+        let ip = |value: &str| Some(value.parse::<IpAddr>().unwrap());
+
+        assert_eq!(
+            client("10.0.0.1", Some("203.0.113.7, 10.0.0.5")),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            client("10.0.0.1", Some("198.51.100.1, 203.0.113.7")),
+            ip("203.0.113.7")
+        );
+        assert_eq!(client("10.0.0.1", Some("junk, 10.0.0.5")), ip("10.0.0.5"));
+        assert_eq!(client("10.0.0.1", None), ip("10.0.0.1"));
+        assert_eq!(
+            client("198.51.100.1", Some("127.0.0.1")),
+            ip("198.51.100.1")
+        );
+        assert_eq!(
+            service.client_ip(&TestRequest::default().to_srv_request()),
+            None
+        );
     }
 }
@@ -0,0 +1,140 @@
+use crate::builder::RedirectSchemeBuilder;
+use crate::error::ConfigError;
+use crate::scheme::RedirectScheme;
+use crate::service::RedirectSchemeService;
+use actix_service::{Service, Transform};
+use actix_web::{
+    dev::{ServiceRequest, ServiceResponse},
+    Error,
+};
+use arc_swap::ArcSwap;
+use futures::future::{ok, LocalBoxFuture, Ready};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Middleware whose configuration is reloaded from a TOML file whenever the file changes.
+///
+/// A background thread checks the modification time of the file at the given interval and stops
+/// when the middleware is dropped. Changes which can't be loaded keep the previous configuration.
+///
+/// ```rust,no_run
+/// use actix_web::{App, web, HttpResponse};
+/// use actix_web_middleware_redirect_scheme::WatchedRedirectScheme;
+/// use std::time::Duration;
+///
+/// let redirect = WatchedRedirectScheme::new("redirect.toml", Duration::from_secs(5)).unwrap();
+///
+/// App::new()
+///     .wrap(redirect)
+///     .route("/", web::get().to(|| HttpResponse::Ok()
+///                                     .content_type("text/plain")
+///                                     .body("Always HTTPS, configured at runtime!")));
+/// ```
+#[derive(Clone)]
+pub struct WatchedRedirectScheme {
+    current: Arc<ArcSwap<RedirectScheme>>,
+}
+
+impl WatchedRedirectScheme {
+    /// Load the configuration file and watch it for changes
+    pub fn new<P: Into<PathBuf>>(path: P, interval: Duration) -> Result<Self, ConfigError> {
+        let path = path.into();
+        let current = Arc::new(ArcSwap::from_pointee(load(&path)?));
+        let watched = Arc::downgrade(&current);
+        let mut last_modified = modified(&path);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            let current = match watched.upgrade() {
+                Some(current) => current,
+                None => break,
+            };
+            let now = modified(&path);
+            if now != last_modified {
+                last_modified = now;
+                if let Ok(scheme) = load(&path) {
+                    current.store(Arc::new(scheme));
+                }
+            }
+        });
+        Ok(WatchedRedirectScheme { current })
+    }
+
+    /// Configuration currently in use
+    pub fn current(&self) -> Arc<RedirectScheme> {
+        self.current.load_full()
+    }
+}
+
+// Configuration of the middleware from the TOML file
+fn load(path: &Path) -> Result<RedirectScheme, ConfigError> {
+    RedirectSchemeBuilder::from_toml_file(path)?.try_build()
+}
+
+// Modification time of the file, `None` if it can't be read
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+pub struct WatchedRedirectSchemeService<S> {
+    service: Rc<RefCell<S>>,
+    current: Arc<ArcSwap<RedirectScheme>>,
+    // Configuration in use and its service, rebuilt when the configuration changes
+    cached: (Arc<RedirectScheme>, RedirectSchemeService<()>),
+}
+
+impl<S, B> Service for WatchedRedirectSchemeService<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let scheme = self.current.load_full();
+        if !Arc::ptr_eq(&scheme, &self.cached.0) {
+            let policy = scheme.service(Rc::new(RefCell::new(())));
+            self.cached = (scheme, policy);
+        }
+        self.cached.1.dispatch(&self.service, req)
+    }
+}
+
+impl<S, B> Transform<S> for WatchedRedirectScheme
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = WatchedRedirectSchemeService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let scheme = self.current.load_full();
+        let policy = scheme.service(Rc::new(RefCell::new(())));
+        ok(WatchedRedirectSchemeService {
+            service: Rc::new(RefCell::new(service)),
+            current: self.current.clone(),
+            cached: (scheme, policy),
+        })
+    }
+}
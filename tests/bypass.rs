@@ -0,0 +1,291 @@
+mod common;
+
+use actix_web::guard;
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use actix_web_middleware_redirect_scheme::RedirectSchemeBuilder;
+use common::{call, peer};
+
+#[actix_rt::test]
+async fn bypass_header() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .bypass_header("x-internal")
+            .build(),
+        TestRequest::default().header("x-internal", "yes"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .bypass_header_secret("x-bypass", "s3cret")
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::default().header("x-bypass", "s3cret"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(scheme(), TestRequest::default().header("x-bypass", "guess")).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+    let res = call(scheme(), TestRequest::default()).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn bypass_user_agents() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .bypass_user_agents(&["Kube-Probe", "ELB-HealthChecker"])
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::default().header("user-agent", "kube-probe/1.27"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        scheme(),
+        TestRequest::default().header("user-agent", "Mozilla/5.0"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn health_check_preset() {
+    let scheme = || RedirectSchemeBuilder::new().ignore_health_checks().build();
+    for path in &["/healthz", "/livez", "/readyz", "/ping", "/status"] {
+        let res = call(scheme(), TestRequest::with_uri(path)).await;
+        assert_eq!(res.status(), StatusCode::OK, "{}", path);
+    }
+    let res = call(scheme(), TestRequest::with_uri("/status/page")).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn certificate_validation_presets() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .allow_acme_challenge()
+            .allow_pki_validation()
+            .build()
+    };
+    for path in &[
+        "/.well-known/acme-challenge/token",
+        "/.well-known/pki-validation/file.txt",
+    ] {
+        let res = call(scheme(), TestRequest::with_uri(path)).await;
+        assert_eq!(res.status(), StatusCode::OK, "{}", path);
+    }
+    let res = call(scheme(), TestRequest::with_uri("/.well-known/security.txt")).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn bypass_client_networks() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .trust_forwarded_from(&["10.0.0.0/8"])
+            .bypass_cidrs(&["192.168.0.0/16", "203.0.113.7"])
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::default().peer_addr(peer("192.168.1.20")),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        scheme(),
+        TestRequest::default()
+            .peer_addr(peer("10.0.0.1"))
+            .header("x-forwarded-for", "203.0.113.7"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        scheme(),
+        TestRequest::default().peer_addr(peer("198.51.100.1")),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn skip_loopback_clients() {
+    let scheme = || RedirectSchemeBuilder::new().skip_loopback(true).build();
+    for ip in &["127.0.0.1", "::1"] {
+        let res = call(scheme(), TestRequest::default().peer_addr(peer(ip))).await;
+        assert_eq!(res.status(), StatusCode::OK, "{}", ip);
+    }
+    let res = call(
+        scheme(),
+        TestRequest::default().peer_addr(peer("192.0.2.1")),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn bypass_query_parameter() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .bypass_query_flag("plain")
+            .build(),
+        TestRequest::with_uri("/?a=1&plain"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .bypass_query_param("token", "s3cret")
+            .build()
+    };
+    let res = call(scheme(), TestRequest::with_uri("/?token=s3cret")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(scheme(), TestRequest::with_uri("/?token=guess")).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+    let res = call(scheme(), TestRequest::with_uri("/?tokens=s3cret")).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn bypass_cookie() {
+    let scheme = || RedirectSchemeBuilder::new().bypass_cookie("no_tls").build();
+    let res = call(
+        scheme(),
+        TestRequest::default().header("cookie", "session=abc; no_tls=1"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        scheme(),
+        TestRequest::default().header("cookie", "session=no_tls"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn predicates_must_all_hold() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .when(|req| req.path() != "/internal")
+            .when(|req| !req.headers().contains_key("x-no-redirect"))
+            .build()
+    };
+    let res = call(scheme(), TestRequest::with_uri("/internal")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        scheme(),
+        TestRequest::default().header("x-no-redirect", "1"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(scheme(), TestRequest::default()).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn forced_redirection_overrides_bypass_rules() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .ignore_path("/admin")
+            .bypass_user_agents(&["kube-probe"])
+            .when(|req| !req.headers().contains_key("x-no-redirect"))
+            .force_when(|req| req.path().starts_with("/admin/login"))
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::with_uri("/admin/login")
+            .header("user-agent", "kube-probe/1.27")
+            .header("x-no-redirect", "1"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+    let res = call(scheme(), TestRequest::with_uri("/admin/users")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        scheme(),
+        TestRequest::with_uri("/admin/login").header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .enable(false)
+            .force_when(|_| true)
+            .build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn guards_skip_redirection() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .guard(|| guard::Header("x-internal", "1"))
+            .build()
+    };
+    let res = call(scheme(), TestRequest::default().header("x-internal", "1")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(scheme(), TestRequest::default()).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .guard(|| guard::Not(guard::Any(guard::Get()).or(guard::Head())))
+            .build()
+    };
+    let res = call(scheme(), TestRequest::post()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(scheme(), TestRequest::get()).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn ignored_websocket_upgrades() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .ignore_websocket_upgrades(true)
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::default()
+            .header("connection", "keep-alive, Upgrade")
+            .header("upgrade", "websocket"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        scheme(),
+        TestRequest::default().header("upgrade", "websocket"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
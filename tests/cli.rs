@@ -0,0 +1,46 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use actix_web_middleware_redirect_scheme::{RedirectSchemeArgs, RedirectSchemeBuilder};
+use clap::Parser;
+use common::{call, location};
+
+#[derive(Parser)]
+struct Cli {
+    #[clap(flatten)]
+    redirect: RedirectSchemeArgs,
+}
+
+#[actix_rt::test]
+async fn command_line_arguments() {
+    let cli = Cli::try_parse_from([
+        "server",
+        "--redirect-https",
+        "--redirect-temporary",
+        "--redirect-https-port",
+        "8443",
+        "--redirect-ignore-path",
+        "/health",
+    ])
+    .unwrap();
+    let service = || RedirectSchemeBuilder::from(&cli.redirect).build();
+
+    let res = call(service(), TestRequest::with_uri("/p")).await;
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(location(&res), Some("https://localhost:8443/p"));
+    let res = call(service(), TestRequest::with_uri("/health")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let cli = Cli::try_parse_from(["server"]).unwrap();
+    let res = call(
+        RedirectSchemeBuilder::from(cli.redirect).build(),
+        TestRequest::with_uri("/p"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    assert!(Cli::try_parse_from(["server", "--redirect-https", "--redirect-http"]).is_err());
+}
@@ -0,0 +1,33 @@
+#![allow(dead_code)]
+
+use actix_web::dev::ServiceResponse;
+use actix_web::test::{self, TestRequest};
+use actix_web::{web, App, HttpResponse};
+use actix_web_middleware_redirect_scheme::RedirectScheme;
+use std::net::SocketAddr;
+
+// Response of an application answering "200 OK" to every request behind the middleware
+pub async fn call(scheme: RedirectScheme, req: TestRequest) -> ServiceResponse {
+    let mut app = test::init_service(
+        App::new()
+            .wrap(scheme)
+            .default_service(web::route().to(HttpResponse::Ok)),
+    )
+    .await;
+    test::call_service(&mut app, req.to_request()).await
+}
+
+// Value of a response header
+pub fn header<'a>(res: &'a ServiceResponse, name: &str) -> Option<&'a str> {
+    res.headers().get(name).map(|value| value.to_str().unwrap())
+}
+
+// `Location` header of a response
+pub fn location(res: &ServiceResponse) -> Option<&str> {
+    header(res, "location")
+}
+
+// Socket address of a peer with the IP address
+pub fn peer(ip: &str) -> SocketAddr {
+    SocketAddr::new(ip.parse().unwrap(), 40000)
+}
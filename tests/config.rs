@@ -0,0 +1,345 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use actix_web_middleware_redirect_scheme::{
+    ConfigError, Profile, RedirectConfig, RedirectSchemeBuilder, ReplacementScope,
+};
+use common::{call, header, location, peer};
+use std::env;
+use std::time::Duration;
+
+#[test]
+fn hsts_preload_requirements() {
+    let err = RedirectSchemeBuilder::new()
+        .hsts(Duration::from_secs(31_536_000))
+        .hsts_preload(true)
+        .try_build()
+        .err();
+    assert_eq!(err, Some(ConfigError::InvalidHstsPreload));
+
+    let err = RedirectSchemeBuilder::new()
+        .hsts(Duration::from_secs(86_400))
+        .hsts_include_subdomains(true)
+        .hsts_preload(true)
+        .try_build()
+        .err();
+    assert_eq!(err, Some(ConfigError::InvalidHstsPreload));
+
+    assert!(RedirectSchemeBuilder::new()
+        .hsts(Duration::from_secs(63_072_000))
+        .hsts_include_subdomains(true)
+        .hsts_preload(true)
+        .try_build()
+        .is_ok());
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn invalid_ignore_regex() {
+    let err = RedirectSchemeBuilder::new()
+        .ignore_path_regex("^/(health")
+        .try_build()
+        .err();
+    assert!(matches!(err, Some(ConfigError::InvalidIgnorePattern(_))));
+}
+
+#[test]
+fn invalid_replacements() {
+    let invalid = |builder: &mut RedirectSchemeBuilder| {
+        matches!(
+            builder.try_build().err(),
+            Some(ConfigError::InvalidReplacement(_))
+        )
+    };
+    assert!(invalid(
+        RedirectSchemeBuilder::new().replacements(&[("", "x")])
+    ));
+    assert!(invalid(
+        RedirectSchemeBuilder::new().replacements(&[(":80", ": 443")])
+    ));
+    assert!(invalid(
+        RedirectSchemeBuilder::new().replacements(&[(":8080", ":8443"), (":8443", ":9443")])
+    ));
+    assert!(invalid(
+        RedirectSchemeBuilder::new()
+            .replace_in(ReplacementScope::Whole, "a", "b")
+            .replacements(&[("a", "c")])
+    ));
+
+    assert!(RedirectSchemeBuilder::new()
+        .replacements(&[(":8080", ":8443")])
+        .replace_in(ReplacementScope::Path, ":8443", ":9443")
+        .try_build()
+        .is_ok());
+}
+
+#[actix_rt::test]
+async fn config_is_applied_to_builder() {
+    let config = RedirectConfig {
+        temporary: Some(true),
+        replacements: vec![(":8080".to_owned(), ":8443".to_owned())],
+        ignore_paths: vec!["/health".to_owned()],
+        ..Default::default()
+    };
+    let service = || RedirectSchemeBuilder::from_config(&config).unwrap().build();
+
+    let res = call(service(), TestRequest::with_uri("/p")).await;
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(location(&res), Some("https://localhost:8443/p"));
+
+    let res = call(service(), TestRequest::with_uri("/health")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let config = RedirectConfig {
+        status_code: Some(200),
+        ..Default::default()
+    };
+    assert_eq!(
+        RedirectSchemeBuilder::from_config(&config).err(),
+        Some(ConfigError::InvalidStatusCode(200))
+    );
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn toml_config() {
+    let config = RedirectConfig::from_toml(
+        r#"
+        temporary = true
+        status_code = 308
+        replacements = [[":8080", ":8443"]]
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.temporary, Some(true));
+    assert_eq!(config.status_code, Some(308));
+    assert_eq!(
+        config.replacements,
+        vec![(":8080".to_owned(), ":8443".to_owned())]
+    );
+
+    assert!(matches!(
+        RedirectConfig::from_toml("unknown = 1"),
+        Err(ConfigError::InvalidConfig(_))
+    ));
+    assert!(matches!(
+        RedirectSchemeBuilder::from_toml_file("/nonexistent/redirect.toml").err(),
+        Some(ConfigError::UnreadableConfig(_))
+    ));
+}
+
+#[test]
+fn env_config() {
+    env::set_var("REDIRECT_SCHEME_TEMPORARY", "yes");
+    env::set_var("REDIRECT_SCHEME_STATUS_CODE", " 308 ");
+    env::set_var("REDIRECT_SCHEME_IGNORE_PATHS", "/health, ,/metrics");
+    let config = RedirectConfig::from_env();
+    env::set_var("REDIRECT_SCHEME_TEMPORARY", "maybe");
+    let invalid = RedirectConfig::from_env();
+    env::remove_var("REDIRECT_SCHEME_TEMPORARY");
+    env::remove_var("REDIRECT_SCHEME_STATUS_CODE");
+    env::remove_var("REDIRECT_SCHEME_IGNORE_PATHS");
+
+    let config = config.unwrap();
+    assert_eq!(config.temporary, Some(true));
+    assert_eq!(config.status_code, Some(308));
+    assert_eq!(config.ignore_paths, vec!["/health", "/metrics"]);
+    assert_eq!(config.enable, None);
+    assert!(matches!(invalid, Err(ConfigError::InvalidConfig(_))));
+}
+
+#[cfg(feature = "figment-config")]
+#[test]
+fn figment_config() {
+    use figment::{providers::Serialized, Figment};
+
+    let defaults = RedirectConfig {
+        temporary: Some(true),
+        status_code: Some(307),
+        ..Default::default()
+    };
+    let figment = Figment::from(defaults).merge(Serialized::default("status_code", 308));
+    let config = RedirectConfig::from_figment(&figment).unwrap();
+    assert_eq!(config.temporary, Some(true));
+    assert_eq!(config.status_code, Some(308));
+
+    let figment = Figment::from(Serialized::default("status_code", "moved"));
+    assert!(matches!(
+        RedirectSchemeBuilder::from_figment(&figment).err(),
+        Some(ConfigError::InvalidConfig(_))
+    ));
+}
+
+#[test]
+fn invalid_ports_paths_and_directions() {
+    assert_eq!(
+        RedirectSchemeBuilder::new().https_port(0).try_build().err(),
+        Some(ConfigError::InvalidPort(0))
+    );
+    assert_eq!(
+        RedirectSchemeBuilder::new()
+            .listen_ports(8080, 8080)
+            .try_build()
+            .err(),
+        Some(ConfigError::InvalidPort(8080))
+    );
+    assert!(matches!(
+        RedirectSchemeBuilder::new()
+            .ignore_paths(vec!["health"])
+            .try_build()
+            .err(),
+        Some(ConfigError::InvalidIgnorePattern(_))
+    ));
+    assert_eq!(
+        RedirectSchemeBuilder::new()
+            .https_to_http()
+            .hsts(Duration::from_secs(86_400))
+            .try_build()
+            .err(),
+        Some(ConfigError::ConflictingDirection("hsts"))
+    );
+    assert_eq!(
+        RedirectSchemeBuilder::new()
+            .https_to_http()
+            .upgrade_required(true)
+            .try_build()
+            .err(),
+        Some(ConfigError::ConflictingDirection("upgrade_required"))
+    );
+}
+
+#[test]
+fn conflicting_settings() {
+    assert_eq!(
+        RedirectSchemeBuilder::new()
+            .temporary()
+            .status_code(StatusCode::PERMANENT_REDIRECT)
+            .try_build()
+            .err(),
+        Some(ConfigError::ConflictingSettings("temporary", "status_code"))
+    );
+    assert_eq!(
+        RedirectSchemeBuilder::new()
+            .canonical_host("www.example.com")
+            .redirect_host("example.org", "www.example.org")
+            .try_build()
+            .err(),
+        Some(ConfigError::ConflictingSettings(
+            "canonical_host",
+            "redirect_host"
+        ))
+    );
+    assert_eq!(
+        RedirectSchemeBuilder::new()
+            .reject(StatusCode::FORBIDDEN, "Use HTTPS")
+            .upgrade_required(true)
+            .try_build()
+            .err(),
+        Some(ConfigError::ConflictingSettings(
+            "reject",
+            "upgrade_required"
+        ))
+    );
+}
+
+#[actix_rt::test]
+async fn deployment_profiles() {
+    let service = |profile| {
+        RedirectSchemeBuilder::new()
+            .profile(profile)
+            .try_build()
+            .unwrap()
+    };
+
+    let req = || TestRequest::with_uri("/p").header("host", "example.com:8080");
+    let res = call(service(Profile::Dev), req().peer_addr(peer("192.0.2.1"))).await;
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(location(&res), Some("https://example.com:8443/p"));
+    let res = call(service(Profile::Dev), req().peer_addr(peer("127.0.0.1"))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(service(Profile::Staging), req()).await;
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+
+    let res = call(
+        service(Profile::Prod),
+        TestRequest::with_uri("/p").header("host", "Example.com"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(location(&res), Some("https://example.com/p"));
+    let res = call(service(Profile::Prod), TestRequest::with_uri("/p")).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = call(
+        service(Profile::Prod),
+        TestRequest::with_uri("/p")
+            .header("host", "example.com")
+            .header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        header(&res, "strict-transport-security"),
+        Some("max-age=31536000")
+    );
+}
+
+#[actix_rt::test]
+async fn profile_status_can_be_tweaked() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .profile(Profile::Prod)
+            .temporary()
+            .try_build()
+            .unwrap(),
+        TestRequest::with_uri("/p").header("host", "example.com"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .profile(Profile::Dev)
+            .status_code(StatusCode::FOUND)
+            .try_build()
+            .unwrap(),
+        TestRequest::with_uri("/p").peer_addr(peer("192.0.2.1")),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::FOUND);
+}
+
+#[test]
+fn invalid_ignore_routes() {
+    for route in &[
+        "/users/{id",
+        "/users/{id}/{id}",
+        "/users/{1id}",
+        "/users/{id:(}",
+        "/files/{tail:.*}*",
+    ] {
+        let err = RedirectSchemeBuilder::new()
+            .ignore_path_route(route)
+            .try_build()
+            .err();
+        assert!(
+            matches!(err, Some(ConfigError::InvalidIgnorePattern(_))),
+            "{}",
+            route
+        );
+    }
+    assert!(RedirectSchemeBuilder::new()
+        .ignore_path_route("/users/{id:\\d+}/avatar")
+        .ignore_path_route("/files/{tail}*")
+        .try_build()
+        .is_ok());
+}
+
+#[test]
+#[should_panic(expected = "unbalanced braces")]
+fn build_panics_on_invalid_configuration() {
+    RedirectSchemeBuilder::new()
+        .ignore_path_route("/users/{id")
+        .build();
+}
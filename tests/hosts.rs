@@ -0,0 +1,120 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use actix_web_middleware_redirect_scheme::RedirectSchemeBuilder;
+use common::call;
+
+#[actix_rt::test]
+async fn host_allowlist() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .only_hosts(&["Example.com"])
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::default().header("host", "example.com:8080"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+    let res = call(
+        scheme(),
+        TestRequest::default().header("host", "other.example"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .only_hosts(&["example.com"])
+            .unknown_host_status(StatusCode::MISDIRECTED_REQUEST)
+            .build(),
+        TestRequest::default().header("host", "other.example"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MISDIRECTED_REQUEST);
+}
+
+#[actix_rt::test]
+async fn host_denylist() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .ignore_hosts(&["internal.example"])
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::default().header("host", "Internal.Example:8080"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        scheme(),
+        TestRequest::default().header("host", "example.com"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn per_host_policies() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .host_policy(
+                "legacy.example",
+                RedirectSchemeBuilder::new().enable(false).build(),
+            )
+            .host_policy(
+                "shop.example",
+                RedirectSchemeBuilder::new()
+                    .status_code(StatusCode::FOUND)
+                    .build(),
+            )
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::default().header("host", "legacy.example"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        scheme(),
+        TestRequest::default().header("host", "Shop.Example"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::FOUND);
+
+    let res = call(
+        scheme(),
+        TestRequest::default().header("host", "example.com"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn wildcard_hosts() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .ignore_hosts(&["*.internal.example"])
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::default().header("host", "db.internal.example"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        scheme(),
+        TestRequest::default().header("host", "internal.example"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
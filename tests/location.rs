@@ -0,0 +1,422 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::middleware::normalize::TrailingSlash;
+use actix_web::test::TestRequest;
+use actix_web_middleware_redirect_scheme::{
+    HostSource, LocationEncoding, RedirectSchemeBuilder, ReplacementScope,
+};
+use common::{call, location};
+
+#[actix_rt::test]
+async fn listener_ports_are_swapped() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .listen_ports(8080, 8443)
+            .build(),
+        TestRequest::with_uri("/p"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(location(&res), Some("https://localhost:8443/p"));
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .https_to_http()
+            .listen_ports(8080, 8443)
+            .build(),
+        TestRequest::with_uri("/p")
+            .header("host", "example.com:8443")
+            .header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("http://example.com:8080/p"));
+
+    let res = call(
+        RedirectSchemeBuilder::new().listen_ports(80, 443).build(),
+        TestRequest::with_uri("/p").header("host", "example.com"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://example.com/p"));
+}
+
+#[actix_rt::test]
+async fn host_source() {
+    let req = || {
+        TestRequest::with_uri("/p")
+            .header("host", "internal.example")
+            .header("forwarded", "host=public.example")
+    };
+    let res = call(RedirectSchemeBuilder::new().build(), req()).await;
+    assert_eq!(location(&res), Some("https://public.example/p"));
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .host_source(HostSource::HostHeader)
+            .build(),
+        req(),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://internal.example/p"));
+}
+
+#[actix_rt::test]
+async fn separate_listener_ports() {
+    let res = call(
+        RedirectSchemeBuilder::new().https_port(8443).build(),
+        TestRequest::with_uri("/p?q=:8080").header("host", "example.com:8080"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://example.com:8443/p?q=:8080"));
+}
+
+#[actix_rt::test]
+async fn canonical_host() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .canonical_host("www.example.com")
+            .build(),
+        TestRequest::with_uri("/p").header("host", "example.com"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://www.example.com/p"));
+}
+
+#[actix_rt::test]
+async fn www_prefix() {
+    let req = |host| TestRequest::with_uri("/p").header("host", host);
+    let res = call(
+        RedirectSchemeBuilder::new().add_www().build(),
+        req("example.com"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://www.example.com/p"));
+    let res = call(
+        RedirectSchemeBuilder::new().add_www().build(),
+        req("localhost"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://localhost/p"));
+
+    let res = call(
+        RedirectSchemeBuilder::new().remove_www().build(),
+        req("www.example.com:8080"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://example.com:8080/p"));
+}
+
+#[actix_rt::test]
+async fn host_rewrites() {
+    let builder = || {
+        let mut builder = RedirectSchemeBuilder::new();
+        builder.rewrite_host("old.example.com", "new.example.com");
+        builder
+    };
+    let res = call(
+        builder().build(),
+        TestRequest::with_uri("/p").header("host", "OLD.example.com:8080"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://new.example.com:8080/p"));
+
+    let res = call(
+        builder().build(),
+        TestRequest::with_uri("/p").header("host", "other.example.com"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://other.example.com/p"));
+}
+
+#[actix_rt::test]
+async fn strip_query() {
+    let res = call(
+        RedirectSchemeBuilder::new().strip_query(true).build(),
+        TestRequest::with_uri("/p?token=secret"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://localhost:8080/p"));
+}
+
+#[actix_rt::test]
+async fn appended_query_params() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .append_query("from", "http")
+            .append_query("note", "a b&c")
+            .build(),
+        TestRequest::with_uri("/p?q=1"),
+    )
+    .await;
+    assert_eq!(
+        location(&res),
+        Some("https://localhost:8080/p?q=1&from=http&note=a%20b%26c")
+    );
+}
+
+#[actix_rt::test]
+async fn path_prefix_rewrites() {
+    let service = || {
+        RedirectSchemeBuilder::new()
+            .rewrite_path_prefix("/old-app", "/app")
+            .build()
+    };
+    let res = call(service(), TestRequest::with_uri("/old-app/users?q=1")).await;
+    assert_eq!(location(&res), Some("https://localhost:8080/app/users?q=1"));
+
+    let res = call(service(), TestRequest::with_uri("/old-apps")).await;
+    assert_eq!(location(&res), Some("https://localhost:8080/old-apps"));
+}
+
+#[actix_rt::test]
+async fn trailing_slash_normalization() {
+    let redirect = |slash, uri| {
+        call(
+            RedirectSchemeBuilder::new().trailing_slash(slash).build(),
+            TestRequest::with_uri(uri),
+        )
+    };
+    let res = redirect(TrailingSlash::Trim, "/p/?q=1").await;
+    assert_eq!(location(&res), Some("https://localhost:8080/p?q=1"));
+    let res = redirect(TrailingSlash::Always, "/p").await;
+    assert_eq!(location(&res), Some("https://localhost:8080/p/"));
+    let res = redirect(TrailingSlash::MergeOnly, "/a//b/").await;
+    assert_eq!(location(&res), Some("https://localhost:8080/a/b/"));
+}
+
+#[actix_rt::test]
+async fn raw_path() {
+    let res = call(
+        RedirectSchemeBuilder::new().build(),
+        TestRequest::with_uri("/a//b/../c"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://localhost:8080/a/c"));
+
+    let res = call(
+        RedirectSchemeBuilder::new().raw_path(true).build(),
+        TestRequest::with_uri("/a//b/../c"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://localhost:8080/a//b/../c"));
+}
+
+#[actix_rt::test]
+async fn mixed_case_hosts() {
+    let req = || TestRequest::with_uri("/p").header("host", "Example.COM");
+    let res = call(
+        RedirectSchemeBuilder::new().lowercase_host(true).build(),
+        req(),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://example.com/p"));
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .mixed_case_host_status(StatusCode::BAD_REQUEST)
+            .build(),
+        req(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(location(&res), None);
+}
+
+#[actix_rt::test]
+async fn invalid_location_is_rejected() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .canonical_host("bad host")
+            .build(),
+        TestRequest::with_uri("/p"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(location(&res), None);
+}
+
+#[actix_rt::test]
+async fn ipv6_hosts() {
+    let res = call(
+        RedirectSchemeBuilder::new().https_port(8443).build(),
+        TestRequest::with_uri("/p").header("host", "[::1]:8080"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://[::1]:8443/p"));
+}
+
+#[cfg(feature = "idna")]
+#[actix_rt::test]
+async fn punycode_hosts() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .canonical_host("bücher.example")
+            .punycode_host(true)
+            .build(),
+        TestRequest::with_uri("/p"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://xn--bcher-kva.example/p"));
+}
+
+#[actix_rt::test]
+async fn location_encoding() {
+    let redirect = |encoding| {
+        call(
+            RedirectSchemeBuilder::new()
+                .rewrite_path_prefix("/old", "/new path")
+                .location_encoding(encoding)
+                .build(),
+            TestRequest::with_uri("/old/p"),
+        )
+    };
+    let res = redirect(LocationEncoding::Encode).await;
+    assert_eq!(location(&res), Some("https://localhost:8080/new%20path/p"));
+
+    let res = redirect(LocationEncoding::Reject).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(location(&res), None);
+}
+
+#[actix_rt::test]
+async fn scoped_replacements() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .replace_in(ReplacementScope::Host, ":8080", ":8443")
+            .replace_in(ReplacementScope::Query, "old", "new")
+            .build(),
+        TestRequest::with_uri("/report:8080/old?v=old"),
+    )
+    .await;
+    assert_eq!(
+        location(&res),
+        Some("https://localhost:8443/report:8080/old?v=new")
+    );
+}
+
+#[actix_rt::test]
+async fn location_template() {
+    let redirect = |template| {
+        call(
+            RedirectSchemeBuilder::new()
+                .https_port(8443)
+                .location_template(template)
+                .build(),
+            TestRequest::with_uri("/p?q=1").header("host", "example.com"),
+        )
+    };
+    let res = redirect("{scheme}://secure.{host}:{https_port}/app{path}{query}").await;
+    assert_eq!(
+        location(&res),
+        Some("https://secure.example.com:8443/app/p?q=1")
+    );
+
+    let res = redirect("{scheme}://bad host{path}").await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn url_mapper() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .map_url(|url, req| format!("{}?sig={}", url, req.path().len()))
+            .build(),
+        TestRequest::with_uri("/path"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://localhost:8080/path?sig=5"));
+}
+
+#[actix_rt::test]
+async fn requests_without_host() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .fallback_host("example.com")
+            .build(),
+        TestRequest::with_uri("/p"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://example.com/p"));
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .fallback_host("example.com")
+            .build(),
+        TestRequest::with_uri("/p").header("host", "other.example.com"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://other.example.com/p"));
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .missing_host_status(StatusCode::BAD_REQUEST)
+            .build(),
+        TestRequest::with_uri("/p"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn userinfo_hosts() {
+    let req = || TestRequest::with_uri("/p").header("host", "user:pass@example.com");
+    let res = call(RedirectSchemeBuilder::new().build(), req()).await;
+    assert_eq!(location(&res), Some("https://example.com/p"));
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .userinfo_status(StatusCode::BAD_REQUEST)
+            .build(),
+        req(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn preserve_port() {
+    let res = call(
+        RedirectSchemeBuilder::new().preserve_port(false).build(),
+        TestRequest::with_uri("/p").header("host", "example.com:8080"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://example.com/p"));
+
+    let res = call(
+        RedirectSchemeBuilder::new().http_port(8080).build(),
+        TestRequest::with_uri("/p").header("host", "example.com:8080"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://example.com/p"));
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .https_to_http()
+            .https_port(8443)
+            .build(),
+        TestRequest::with_uri("/p")
+            .header("host", "example.com:8443")
+            .header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("http://example.com/p"));
+}
+
+#[actix_rt::test]
+async fn cross_domain_targets() {
+    let service = || {
+        let mut builder = RedirectSchemeBuilder::new();
+        builder
+            .https_port(8443)
+            .redirect_host("legacy.example.com", "app.example.com")
+            .redirect_host("*.old.example", "new.example:9443");
+        builder.build()
+    };
+    let req = |host| TestRequest::with_uri("/p").header("host", host);
+    let res = call(service(), req("legacy.example.com:8080")).await;
+    assert_eq!(location(&res), Some("https://app.example.com/p"));
+    let res = call(service(), req("www.old.example")).await;
+    assert_eq!(location(&res), Some("https://new.example:9443/p"));
+    let res = call(service(), req("other.example.com")).await;
+    assert_eq!(location(&res), Some("https://other.example.com:8443/p"));
+}
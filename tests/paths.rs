@@ -0,0 +1,184 @@
+mod common;
+
+use actix_web::http::{Method, StatusCode};
+use actix_web::test::TestRequest;
+use actix_web_middleware_redirect_scheme::{GlobPattern, PathPattern, RedirectSchemeBuilder};
+use common::call;
+
+#[actix_rt::test]
+async fn path_status_longest_prefix_wins() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .path_status("/api", StatusCode::PERMANENT_REDIRECT)
+            .path_status("/api/v2", StatusCode::TEMPORARY_REDIRECT)
+            .build()
+    };
+    let res = call(scheme(), TestRequest::with_uri("/api/users")).await;
+    assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+
+    let res = call(scheme(), TestRequest::with_uri("/api/v2/users")).await;
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+
+    for path in &["/api", "/api/v2beta"] {
+        let res = call(scheme(), TestRequest::with_uri(path)).await;
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT, "{}", path);
+    }
+
+    for path in &["/", "/apiary"] {
+        let res = call(scheme(), TestRequest::with_uri(path)).await;
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY, "{}", path);
+    }
+}
+
+#[actix_rt::test]
+async fn ignored_prefixes_and_globs() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .ignore_path("/healthz")
+            .ignore_path_glob("/static/**/*.css")
+            .build()
+    };
+    for path in &["/healthz", "/healthz/live", "/static/a/site.css"] {
+        let res = call(scheme(), TestRequest::with_uri(path)).await;
+        assert_eq!(res.status(), StatusCode::OK, "{}", path);
+    }
+    for path in &["/", "/static/site.js"] {
+        let res = call(scheme(), TestRequest::with_uri(path)).await;
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY, "{}", path);
+    }
+}
+
+#[cfg(feature = "regex")]
+#[actix_rt::test]
+async fn ignored_regexes() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .ignore_path_regex("^/(health|metrics)$")
+            .build()
+    };
+    let res = call(scheme(), TestRequest::with_uri("/metrics")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(scheme(), TestRequest::with_uri("/metrics/cpu")).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn ignored_exact_paths() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .ignore_path_exact("/health")
+            .ignore_path_pattern(PathPattern::Glob(GlobPattern::new("/*.txt")))
+            .build()
+    };
+    for path in &["/health", "/robots.txt"] {
+        let res = call(scheme(), TestRequest::with_uri(path)).await;
+        assert_eq!(res.status(), StatusCode::OK, "{}", path);
+    }
+    let res = call(scheme(), TestRequest::with_uri("/health/live")).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn ignored_paths_in_bulk() {
+    let prefixes = vec!["/metrics".to_owned(), "/.well-known/".to_owned()];
+    let scheme = || RedirectSchemeBuilder::new().ignore_paths(&prefixes).build();
+    for path in &["/metrics", "/.well-known/acme-challenge/x"] {
+        let res = call(scheme(), TestRequest::with_uri(path)).await;
+        assert_eq!(res.status(), StatusCode::OK, "{}", path);
+    }
+    let res = call(scheme(), TestRequest::with_uri("/")).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn only_listed_paths_are_redirected() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .only_path("/account")
+            .only_path("/checkout")
+            .build()
+    };
+    let res = call(scheme(), TestRequest::with_uri("/checkout/pay")).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+    let res = call(scheme(), TestRequest::with_uri("/blog")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn ignored_extensions() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .ignore_extensions(&[".css", "JS"])
+            .build()
+    };
+    for path in &["/app.js", "/assets/site.CSS"] {
+        let res = call(scheme(), TestRequest::with_uri(path)).await;
+        assert_eq!(res.status(), StatusCode::OK, "{}", path);
+    }
+    for path in &["/js", "/.css", "/css.d/index"] {
+        let res = call(scheme(), TestRequest::with_uri(path)).await;
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY, "{}", path);
+    }
+}
+
+#[actix_rt::test]
+async fn ignored_methods() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .ignore_methods(&[Method::OPTIONS])
+            .build()
+    };
+    let res = call(scheme(), TestRequest::with_uri("/").method(Method::OPTIONS)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(scheme(), TestRequest::get()).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn ignored_routes() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .ignore_path_route("/users/{id}/avatar")
+            .ignore_path_route("/files/{tail:.*}")
+            .build()
+    };
+    for path in &["/users/42/avatar", "/files/a/b.txt"] {
+        let res = call(scheme(), TestRequest::with_uri(path)).await;
+        assert_eq!(res.status(), StatusCode::OK, "{}", path);
+    }
+    let res = call(scheme(), TestRequest::with_uri("/users/42/profile")).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn large_ignore_lists() {
+    let prefixes: Vec<String> = (0..10_000).map(|i| format!("/tenant/{}/", i)).collect();
+    let scheme = || RedirectSchemeBuilder::new().ignore_paths(&prefixes).build();
+    let res = call(scheme(), TestRequest::with_uri("/tenant/9999/home")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(scheme(), TestRequest::with_uri("/tenant/10000/home")).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn redirected_paths_under_ignored_prefixes() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .ignore_path("/api/")
+            .redirect_path("/api/login")
+            .redirect_path_exact("/api/account")
+            .build()
+    };
+    for path in &["/api/login", "/api/login/callback", "/api/account"] {
+        let res = call(scheme(), TestRequest::with_uri(path)).await;
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY, "{}", path);
+    }
+    for path in &["/api/users", "/api/account/settings"] {
+        let res = call(scheme(), TestRequest::with_uri(path)).await;
+        assert_eq!(res.status(), StatusCode::OK, "{}", path);
+    }
+}
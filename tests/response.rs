@@ -0,0 +1,540 @@
+mod common;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use actix_web::test::{self, TestRequest};
+use actix_web::HttpResponse;
+use actix_web_middleware_redirect_scheme::{
+    ConfigError, RedirectBody, RedirectSchemeBuilder, SchemeHint,
+};
+use common::{call, header, location};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[actix_rt::test]
+async fn permanent_redirect_preserving_method() {
+    let res = call(
+        RedirectSchemeBuilder::new().preserve_method(true).build(),
+        TestRequest::post().uri("/form"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(location(&res), Some("https://localhost:8080/form"));
+}
+
+#[actix_rt::test]
+async fn custom_status_code() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .status_code(StatusCode::SEE_OTHER)
+            .build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+}
+
+#[test]
+fn custom_status_code_must_redirect() {
+    assert_eq!(
+        RedirectSchemeBuilder::new()
+            .status_code(StatusCode::OK)
+            .try_build()
+            .err(),
+        Some(ConfigError::InvalidStatusCode(200))
+    );
+    assert_eq!(
+        RedirectSchemeBuilder::new()
+            .status_for_unsafe_methods(StatusCode::NOT_FOUND)
+            .try_build()
+            .err(),
+        Some(ConfigError::InvalidStatusCode(404))
+    );
+    assert_eq!(
+        RedirectSchemeBuilder::new()
+            .path_status("/api", StatusCode::OK)
+            .try_build()
+            .err(),
+        Some(ConfigError::InvalidStatusCode(200))
+    );
+}
+
+#[actix_rt::test]
+async fn status_for_unsafe_methods() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .status_for_unsafe_methods(StatusCode::PERMANENT_REDIRECT)
+            .build()
+    };
+    let res = call(scheme(), TestRequest::post()).await;
+    assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+
+    let res = call(scheme(), TestRequest::get()).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn strict_mode_rejects_plaintext() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .reject(StatusCode::FORBIDDEN, "HTTPS required")
+            .build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(location(&res), None);
+    assert_eq!(test::read_body(res).await, "HTTPS required");
+}
+
+#[actix_rt::test]
+async fn upgrade_required_response() {
+    let res = call(
+        RedirectSchemeBuilder::new().upgrade_required(true).build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::UPGRADE_REQUIRED);
+    assert_eq!(header(&res, "upgrade"), Some("TLS/1.3"));
+    assert_eq!(header(&res, "connection"), Some("Upgrade"));
+    assert_eq!(location(&res), None);
+}
+
+#[actix_rt::test]
+async fn redirect_bodies() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .redirect_body(RedirectBody::Html)
+            .build(),
+        TestRequest::with_uri("/a?b=1&c=2"),
+    )
+    .await;
+    assert_eq!(
+        header(&res, "content-type"),
+        Some("text/html; charset=utf-8")
+    );
+    let body = test::read_body(res).await;
+    let body = std::str::from_utf8(&body).unwrap();
+    assert!(body.contains("<a href=\"https://localhost:8080/a?b=1&amp;c=2\">"));
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .redirect_body(RedirectBody::Text)
+            .build(),
+        TestRequest::with_uri("/a"),
+    )
+    .await;
+    assert_eq!(
+        header(&res, "content-type"),
+        Some("text/plain; charset=utf-8")
+    );
+    assert_eq!(
+        test::read_body(res).await,
+        "Moved to https://localhost:8080/a\n"
+    );
+
+    let res = call(RedirectSchemeBuilder::new().build(), TestRequest::default()).await;
+    assert!(test::read_body(res).await.is_empty());
+}
+
+#[actix_rt::test]
+async fn negotiated_redirect_body() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .redirect_body(RedirectBody::Negotiate)
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::with_uri("/a").header("accept", "application/json"),
+    )
+    .await;
+    assert_eq!(header(&res, "content-type"), Some("application/json"));
+    assert_eq!(
+        test::read_body(res).await,
+        "{\"location\":\"https://localhost:8080/a\"}"
+    );
+
+    let res = call(
+        scheme(),
+        TestRequest::default().header("accept", "text/html,*/*;q=0.8"),
+    )
+    .await;
+    assert_eq!(
+        header(&res, "content-type"),
+        Some("text/html; charset=utf-8")
+    );
+
+    let res = call(scheme(), TestRequest::default().header("accept", "*/*")).await;
+    assert!(test::read_body(res).await.is_empty());
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .redirect_body(RedirectBody::Json)
+            .json_serializer(|location| format!("{{\"to\":\"{}\"}}", location))
+            .build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(
+        test::read_body(res).await,
+        "{\"to\":\"https://localhost:8080/\"}"
+    );
+}
+
+#[actix_rt::test]
+async fn custom_redirect_headers() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .redirect_header("x-reason", "tls-required")
+            .build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(header(&res, "x-reason"), Some("tls-required"));
+}
+
+#[test]
+fn custom_redirect_header_names_are_validated() {
+    assert_eq!(
+        RedirectSchemeBuilder::new()
+            .redirect_header("bad name", "value")
+            .try_build()
+            .err(),
+        Some(ConfigError::InvalidHeader("bad name: value".to_owned()))
+    );
+    assert_eq!(
+        RedirectSchemeBuilder::new()
+            .redirect_cache_control("max-age=300\n")
+            .try_build()
+            .err(),
+        Some(ConfigError::InvalidHeader(
+            "Cache-Control: max-age=300\n".to_owned()
+        ))
+    );
+}
+
+#[actix_rt::test]
+async fn redirect_cache_control() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .redirect_cache_control("max-age=300")
+            .build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(header(&res, "cache-control"), Some("max-age=300"));
+
+    let res = call(RedirectSchemeBuilder::new().build(), TestRequest::default()).await;
+    assert_eq!(header(&res, "cache-control"), Some("max-age=86400"));
+
+    let res = call(
+        RedirectSchemeBuilder::new().temporary().build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(header(&res, "cache-control"), None);
+}
+
+#[actix_rt::test]
+async fn vary_lists_consulted_headers() {
+    let res = call(RedirectSchemeBuilder::new().build(), TestRequest::default()).await;
+    assert_eq!(
+        header(&res, "vary"),
+        Some("Forwarded, X-Forwarded-Proto, X-Forwarded-Host")
+    );
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .redirect_body(RedirectBody::Negotiate)
+            .build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(
+        header(&res, "vary"),
+        Some("Forwarded, X-Forwarded-Proto, X-Forwarded-Host, Accept")
+    );
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .navigations_only(true)
+            .bypass_header("X-Internal")
+            .bypass_cookie("no_redirect")
+            .bypass_user_agents(&["kube-probe"])
+            .build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(
+        header(&res, "vary"),
+        Some(
+            "Forwarded, X-Forwarded-Proto, X-Forwarded-Host, Sec-Fetch-Dest, X-Internal, Cookie, \
+             User-Agent"
+        )
+    );
+}
+
+#[actix_rt::test]
+async fn hsts_on_https_responses() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .hsts(Duration::from_secs(3600))
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::default().header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        header(&res, "strict-transport-security"),
+        Some("max-age=3600")
+    );
+
+    let res = call(scheme(), TestRequest::default()).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(header(&res, "strict-transport-security"), None);
+}
+
+#[actix_rt::test]
+async fn hsts_on_bypassed_https_responses() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .hsts(Duration::from_secs(3600))
+            .ignore_paths(["/healthz"])
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::with_uri("/healthz").header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        header(&res, "strict-transport-security"),
+        Some("max-age=3600")
+    );
+
+    let res = call(scheme(), TestRequest::with_uri("/healthz")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(header(&res, "strict-transport-security"), None);
+}
+
+#[actix_rt::test]
+async fn hsts_directives() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .hsts(Duration::from_secs(31_536_000))
+            .hsts_include_subdomains(true)
+            .hsts_preload(true)
+            .try_build()
+            .unwrap(),
+        TestRequest::default().header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(
+        header(&res, "strict-transport-security"),
+        Some("max-age=31536000; includeSubDomains; preload")
+    );
+}
+
+#[actix_rt::test]
+async fn refresh_fallbacks() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .refresh_header(true)
+            .meta_refresh(true)
+            .build(),
+        TestRequest::with_uri("/a"),
+    )
+    .await;
+    assert_eq!(
+        header(&res, "refresh"),
+        Some("0; url=https://localhost:8080/a")
+    );
+    assert_eq!(
+        header(&res, "content-type"),
+        Some("text/html; charset=utf-8")
+    );
+    let body = test::read_body(res).await;
+    let body = std::str::from_utf8(&body).unwrap();
+    assert!(
+        body.contains("<meta http-equiv=\"refresh\" content=\"0; url=https://localhost:8080/a\">")
+    );
+}
+
+#[actix_rt::test]
+async fn canonical_link_headers() {
+    let res = call(
+        RedirectSchemeBuilder::new().canonical_link(true).build(),
+        TestRequest::with_uri("/a"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(
+        header(&res, "link"),
+        Some("<https://localhost:8080/a>; rel=\"canonical\"")
+    );
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .canonical_link_pass_through(true)
+            .build(),
+        TestRequest::with_uri("/a").header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        header(&res, "link"),
+        Some("<https://localhost:8080/a>; rel=\"canonical\"")
+    );
+}
+
+#[actix_rt::test]
+async fn redirect_hook() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .on_redirect(|req, res| {
+                let value = HeaderValue::from_str(req.path()).unwrap();
+                res.headers_mut()
+                    .insert(HeaderName::from_static("x-path"), value);
+            })
+            .build(),
+        TestRequest::with_uri("/a"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(header(&res, "x-path"), Some("/a"));
+}
+
+#[actix_rt::test]
+async fn redirect_hook_runs_after_async_resolver() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .async_scheme_resolver(|_| async { Some(SchemeHint::Https) })
+            .on_redirect(move |_, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+}
+
+#[actix_rt::test]
+async fn large_bodies_are_not_redirected() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .body_limit(1024, StatusCode::PAYLOAD_TOO_LARGE)
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::post()
+            .uri("/upload")
+            .header("content-length", "4096"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(
+        test::read_body(res).await,
+        "Request body too large to be redirected, resend it to https://localhost:8080/upload\n"
+    );
+
+    let res = call(
+        scheme(),
+        TestRequest::post()
+            .uri("/upload")
+            .header("content-length", "16"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn custom_responder() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .redirect_body(RedirectBody::Html)
+            .responder(|_: &ServiceRequest, target: &str| {
+                HttpResponse::Found()
+                    .header("location", target)
+                    .body("moved")
+            })
+            .build(),
+        TestRequest::with_uri("/a"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::FOUND);
+    assert_eq!(location(&res), Some("https://localhost:8080/a"));
+    assert_eq!(test::read_body(res).await, "moved");
+}
+
+#[actix_rt::test]
+async fn only_given_methods_are_redirected() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .redirect_methods(&[Method::GET, Method::HEAD])
+            .build()
+    };
+    let res = call(scheme(), TestRequest::get()).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+    let res = call(scheme(), TestRequest::post()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .redirect_only_methods(&[Method::GET])
+            .build(),
+        TestRequest::post(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn diagnostic_header() {
+    let res = call(
+        RedirectSchemeBuilder::new().diagnostic_header(true).build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(
+        header(&res, "x-redirected-by"),
+        Some("redirect-scheme (reason=http_to_https)")
+    );
+
+    let res = call(RedirectSchemeBuilder::new().build(), TestRequest::default()).await;
+    assert_eq!(header(&res, "x-redirected-by"), None);
+}
+
+#[actix_rt::test]
+async fn navigations_only() {
+    let scheme = || RedirectSchemeBuilder::new().navigations_only(true).build();
+    let res = call(
+        scheme(),
+        TestRequest::default().header("sec-fetch-dest", "document"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+    let res = call(
+        scheme(),
+        TestRequest::default().header("sec-fetch-dest", "image"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(scheme(), TestRequest::default()).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
@@ -0,0 +1,747 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use actix_web_middleware_redirect_scheme::{
+    ConfigError, RedirectSchemeBuilder, SchemeHint, SchemeSource,
+};
+use common::{call, location, peer};
+
+#[actix_rt::test]
+async fn forwarded_scheme_trusted_without_proxy_list() {
+    let res = call(
+        RedirectSchemeBuilder::new().build(),
+        TestRequest::default()
+            .peer_addr(peer("203.0.113.7"))
+            .header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn forwarded_scheme_trusted_from_listed_proxies() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .trusted_proxies(&["10.0.0.0/8".parse().unwrap()])
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::default()
+            .peer_addr(peer("10.1.2.3"))
+            .header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        scheme(),
+        TestRequest::default()
+            .peer_addr(peer("203.0.113.7"))
+            .header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(location(&res), Some("https://localhost:8080/"));
+}
+
+#[actix_rt::test]
+async fn requests_without_peer_address_are_untrusted() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .trusted_proxies(&["10.0.0.0/8".parse().unwrap()])
+            .build(),
+        TestRequest::default().header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn forwarded_header_scheme_and_host() {
+    let res = call(
+        RedirectSchemeBuilder::new().build(),
+        TestRequest::default().header("forwarded", "proto=https;host=public.example"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        RedirectSchemeBuilder::new().build(),
+        TestRequest::with_uri("/p?q=1")
+            .header("forwarded", "for=192.0.2.60;proto=http;host=public.example")
+            .header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(location(&res), Some("https://public.example/p?q=1"));
+}
+
+#[actix_rt::test]
+async fn legacy_forwarded_headers_can_be_preferred() {
+    let res = call(
+        RedirectSchemeBuilder::new().prefer_forwarded(false).build(),
+        TestRequest::default()
+            .header("forwarded", "proto=http;host=public.example")
+            .header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        RedirectSchemeBuilder::new().prefer_forwarded(false).build(),
+        TestRequest::with_uri("/p")
+            .header("forwarded", "proto=https;host=public.example")
+            .header("x-forwarded-proto", "http")
+            .header("x-forwarded-host", "legacy.example"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(location(&res), Some("https://legacy.example/p"));
+}
+
+#[actix_rt::test]
+async fn custom_scheme_header_takes_precedence() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .scheme_header("X-Scheme")
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::default().header("x-scheme", " HTTPS "),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        scheme(),
+        TestRequest::default()
+            .header("x-scheme", "http")
+            .header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+    let res = call(
+        scheme(),
+        TestRequest::default()
+            .header("x-scheme", "")
+            .header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn cloudflare_visitor_scheme() {
+    let res = call(
+        RedirectSchemeBuilder::new().behind_cloudflare().build(),
+        TestRequest::default()
+            .header("cf-visitor", "{\"scheme\":\"https\"}")
+            .header("x-forwarded-proto", "http"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        RedirectSchemeBuilder::new().build(),
+        TestRequest::default().header("cf-visitor", "{\"scheme\":\"https\"}"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn cloudfront_forwarded_proto() {
+    let res = call(
+        RedirectSchemeBuilder::new().behind_cloudfront().build(),
+        TestRequest::default()
+            .header("cloudfront-forwarded-proto", "https")
+            .header("x-forwarded-proto", "http"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn azure_arr_ssl_marks_https() {
+    let res = call(
+        RedirectSchemeBuilder::new().behind_azure().build(),
+        TestRequest::default().header("x-arr-ssl", "2048|256|C=US"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        RedirectSchemeBuilder::new().behind_azure().build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn fastly_ssl_marks_https() {
+    let res = call(
+        RedirectSchemeBuilder::new().behind_fastly().build(),
+        TestRequest::default().header("fastly-ssl", "1"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn fly_forwarded_proto() {
+    let res = call(
+        RedirectSchemeBuilder::new().behind_fly().build(),
+        TestRequest::default().header("fly-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        RedirectSchemeBuilder::new().behind_fly().build(),
+        TestRequest::default().header("fly-forwarded-proto", "http"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn scheme_hint_from_extensions() {
+    use actix_web::dev::Service;
+    use actix_web::{test, web, App, HttpMessage, HttpResponse};
+
+    for (hint, status) in &[
+        (SchemeHint::Https, StatusCode::OK),
+        (SchemeHint::Http, StatusCode::MOVED_PERMANENTLY),
+    ] {
+        let hint = *hint;
+        let mut app = test::init_service(
+            App::new()
+                .wrap(RedirectSchemeBuilder::new().build())
+                .wrap_fn(move |req, srv| {
+                    req.extensions_mut().insert(hint);
+                    srv.call(req)
+                })
+                .default_service(web::route().to(HttpResponse::Ok)),
+        )
+        .await;
+        let req = TestRequest::default()
+            .header("x-forwarded-proto", "https")
+            .to_request();
+        assert_eq!(test::call_service(&mut app, req).await.status(), *status);
+    }
+}
+
+#[actix_rt::test]
+async fn unknown_scheme_policy() {
+    use actix_web_middleware_redirect_scheme::Assume;
+
+    for (policy, status) in &[
+        (Assume::Http, StatusCode::MOVED_PERMANENTLY),
+        (Assume::Https, StatusCode::OK),
+        (Assume::PassThrough, StatusCode::OK),
+    ] {
+        let res = call(
+            RedirectSchemeBuilder::new()
+                .unknown_scheme_policy(*policy)
+                .build(),
+            TestRequest::default(),
+        )
+        .await;
+        assert_eq!(res.status(), *status, "{:?}", policy);
+    }
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .unknown_scheme_policy(Assume::PassThrough)
+            .build(),
+        TestRequest::default().header("x-forwarded-proto", "http"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn listener_scheme_ignores_forwarded_headers() {
+    let res = call(
+        RedirectSchemeBuilder::new().listener_scheme(true).build(),
+        TestRequest::default().header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn scheme_sources_in_order() {
+    let req = || {
+        TestRequest::default()
+            .header("x-forwarded-proto", "http")
+            .header("x-scheme", "https")
+    };
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .scheme_header("x-scheme")
+            .scheme_sources(&[SchemeSource::CustomHeader, SchemeSource::XForwardedProto])
+            .build(),
+        req(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .scheme_header("x-scheme")
+            .scheme_sources(&[SchemeSource::XForwardedProto, SchemeSource::CustomHeader])
+            .build(),
+        req(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .scheme_sources(&[SchemeSource::ForwardedHeader])
+            .build(),
+        TestRequest::default().header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn trust_forwarded_from_networks_and_addresses() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .trust_forwarded_from(&["10.0.0.0/8", "192.168.1.1", "fd00::/8"])
+            .build()
+    };
+    for (ip, status) in &[
+        ("10.1.2.3", StatusCode::OK),
+        ("192.168.1.1", StatusCode::OK),
+        ("fd00::1", StatusCode::OK),
+        ("192.168.1.2", StatusCode::MOVED_PERMANENTLY),
+        ("203.0.113.7", StatusCode::MOVED_PERMANENTLY),
+    ] {
+        let res = call(
+            scheme(),
+            TestRequest::default()
+                .peer_addr(peer(ip))
+                .header("x-forwarded-proto", "https"),
+        )
+        .await;
+        assert_eq!(res.status(), *status, "{}", ip);
+    }
+}
+
+#[actix_rt::test]
+async fn trust_forwarded_from_replaces_proxies() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .trust_forwarded_from(&["10.0.0.0/8"])
+            .trust_forwarded_from(&["192.168.0.0/16"])
+            .build(),
+        TestRequest::default()
+            .peer_addr(peer("10.1.2.3"))
+            .header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[test]
+fn invalid_networks_are_reported() {
+    let err = RedirectSchemeBuilder::new()
+        .trust_forwarded_from(&["10.0.0.0/33"])
+        .try_build()
+        .err();
+    assert_eq!(
+        err,
+        Some(ConfigError::InvalidNetwork("10.0.0.0/33".to_owned()))
+    );
+
+    let err = RedirectSchemeBuilder::new()
+        .bypass_cidrs(&["office"])
+        .try_build()
+        .err();
+    assert_eq!(err, Some(ConfigError::InvalidNetwork("office".to_owned())));
+}
+
+#[actix_rt::test]
+async fn h2c_policy() {
+    use actix_web::http::Version;
+    use actix_web_middleware_redirect_scheme::H2cPolicy;
+
+    for (policy, status) in &[
+        (H2cPolicy::Redirect, StatusCode::MOVED_PERMANENTLY),
+        (H2cPolicy::PassThrough, StatusCode::OK),
+        (
+            H2cPolicy::Respond(StatusCode::HTTP_VERSION_NOT_SUPPORTED),
+            StatusCode::HTTP_VERSION_NOT_SUPPORTED,
+        ),
+    ] {
+        let res = call(
+            RedirectSchemeBuilder::new().h2c_policy(*policy).build(),
+            TestRequest::default().version(Version::HTTP_2),
+        )
+        .await;
+        assert_eq!(res.status(), *status, "{:?}", policy);
+    }
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .h2c_policy(H2cPolicy::PassThrough)
+            .build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn websocket_policy() {
+    use actix_web_middleware_redirect_scheme::WebSocketPolicy;
+
+    let handshake = || {
+        TestRequest::with_uri("/chat")
+            .header("connection", "Upgrade")
+            .header("upgrade", "websocket")
+    };
+    for (policy, status, target) in &[
+        (
+            WebSocketPolicy::Redirect,
+            StatusCode::MOVED_PERMANENTLY,
+            Some("https://localhost:8080/chat"),
+        ),
+        (
+            WebSocketPolicy::RedirectWebSocket,
+            StatusCode::MOVED_PERMANENTLY,
+            Some("wss://localhost:8080/chat"),
+        ),
+        (WebSocketPolicy::PassThrough, StatusCode::OK, None),
+    ] {
+        let res = call(
+            RedirectSchemeBuilder::new()
+                .websocket_policy(*policy)
+                .build(),
+            handshake(),
+        )
+        .await;
+        assert_eq!(res.status(), *status, "{:?}", policy);
+        assert_eq!(location(&res), *target, "{:?}", policy);
+    }
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .websocket_policy(WebSocketPolicy::RedirectWebSocket)
+            .build(),
+        TestRequest::with_uri("/chat"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://localhost:8080/chat"));
+}
+
+#[test]
+fn proxy_protocol_info_scheme() {
+    use actix_web_middleware_redirect_scheme::ProxyProtocolInfo;
+
+    let info = |tls, destination_port| ProxyProtocolInfo {
+        tls,
+        destination_port,
+    };
+    assert_eq!(info(Some(true), Some(80)).scheme(), Some("https"));
+    assert_eq!(info(Some(false), Some(443)).scheme(), Some("http"));
+    assert_eq!(info(None, Some(443)).scheme(), Some("https"));
+    assert_eq!(info(None, Some(80)).scheme(), Some("http"));
+    assert_eq!(info(None, Some(8443)).scheme(), None);
+    assert_eq!(ProxyProtocolInfo::default().scheme(), None);
+}
+
+#[actix_rt::test]
+async fn proxy_protocol_info_from_extensions() {
+    use actix_web::dev::Service;
+    use actix_web::{test, web, App, HttpMessage, HttpResponse};
+    use actix_web_middleware_redirect_scheme::ProxyProtocolInfo;
+
+    let mut app = test::init_service(
+        App::new()
+            .wrap(RedirectSchemeBuilder::new().build())
+            .wrap_fn(|req, srv| {
+                req.extensions_mut().insert(ProxyProtocolInfo {
+                    tls: Some(true),
+                    destination_port: None,
+                });
+                srv.call(req)
+            })
+            .default_service(web::route().to(HttpResponse::Ok)),
+    )
+    .await;
+    let req = TestRequest::default()
+        .header("x-forwarded-proto", "http")
+        .to_request();
+    assert_eq!(
+        test::call_service(&mut app, req).await.status(),
+        StatusCode::OK
+    );
+}
+
+#[actix_rt::test]
+async fn forwarded_proto_policy() {
+    use actix_web_middleware_redirect_scheme::ForwardedProtoPolicy;
+
+    for (policy, value, status) in &[
+        (ForwardedProtoPolicy::First, "https, http", StatusCode::OK),
+        (
+            ForwardedProtoPolicy::Last,
+            "https, http",
+            StatusCode::MOVED_PERMANENTLY,
+        ),
+        (ForwardedProtoPolicy::Last, "http,, https ,", StatusCode::OK),
+        (
+            ForwardedProtoPolicy::RequireAllHttps,
+            "https, http",
+            StatusCode::MOVED_PERMANENTLY,
+        ),
+        (
+            ForwardedProtoPolicy::RequireAllHttps,
+            "HTTPS,https",
+            StatusCode::OK,
+        ),
+    ] {
+        let res = call(
+            RedirectSchemeBuilder::new()
+                .forwarded_proto_policy(*policy)
+                .build(),
+            TestRequest::default().header("x-forwarded-proto", *value),
+        )
+        .await;
+        assert_eq!(res.status(), *status, "{:?} {}", policy, value);
+    }
+}
+
+#[actix_rt::test]
+async fn proxy_presets_trust_private_networks() {
+    for preset in &[
+        RedirectSchemeBuilder::preset_nginx_ingress
+            as fn(&mut RedirectSchemeBuilder) -> &mut RedirectSchemeBuilder,
+        RedirectSchemeBuilder::preset_traefik,
+    ] {
+        let scheme = || preset(&mut RedirectSchemeBuilder::new()).build();
+        let res = call(
+            scheme(),
+            TestRequest::default()
+                .peer_addr(peer("10.42.0.7"))
+                .header("x-forwarded-proto", "https"),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = call(
+            scheme(),
+            TestRequest::default()
+                .peer_addr(peer("203.0.113.7"))
+                .header("x-forwarded-proto", "https"),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+        let res = call(
+            scheme(),
+            TestRequest::with_uri("/p")
+                .peer_addr(peer("10.42.0.7"))
+                .header("host", "pod.internal")
+                .header("x-forwarded-host", "example.com"),
+        )
+        .await;
+        assert_eq!(location(&res), Some("https://example.com/p"));
+    }
+
+    let res = call(
+        RedirectSchemeBuilder::new().preset_nginx_ingress().build(),
+        TestRequest::with_uri("/p")
+            .peer_addr(peer("10.42.0.1"))
+            .header("user-agent", "kube-probe/1.27"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn proxy_presets_keep_earlier_settings() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .trust_forwarded_from(&["203.0.113.0/24"])
+            .scheme_sources(&[SchemeSource::CustomHeader])
+            .scheme_header("X-Scheme")
+            .bypass_user_agents(&["Uptime"])
+            .preset_nginx_ingress()
+            .build()
+    };
+
+    let res = call(
+        scheme(),
+        TestRequest::default()
+            .peer_addr(peer("203.0.113.7"))
+            .header("x-scheme", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        scheme(),
+        TestRequest::default()
+            .peer_addr(peer("10.42.0.7"))
+            .header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    for agent in &["uptime-monitor/2.0", "kube-probe/1.27"] {
+        let res = call(
+            scheme(),
+            TestRequest::default()
+                .peer_addr(peer("198.51.100.7"))
+                .header("user-agent", *agent),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let res = call(
+        scheme(),
+        TestRequest::default().peer_addr(peer("198.51.100.7")),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[actix_rt::test]
+async fn heroku_preset_uses_router_scheme() {
+    let res = call(
+        RedirectSchemeBuilder::new().preset_heroku().build(),
+        TestRequest::default().header("x-forwarded-proto", "https, http"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+    let res = call(
+        RedirectSchemeBuilder::new().preset_heroku().build(),
+        TestRequest::default().header("x-forwarded-proto", "http, https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn forwarded_host_header() {
+    let res = call(
+        RedirectSchemeBuilder::new().forwarded_host(true).build(),
+        TestRequest::with_uri("/p").header("x-forwarded-host", "public.example, internal.example"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(location(&res), Some("https://public.example/p"));
+
+    let req = || {
+        TestRequest::with_uri("/p")
+            .header("forwarded", "host=rfc.example")
+            .header("x-forwarded-host", "legacy.example")
+    };
+    let res = call(RedirectSchemeBuilder::new().build(), req()).await;
+    assert_eq!(location(&res), Some("https://rfc.example/p"));
+
+    let res = call(
+        RedirectSchemeBuilder::new().forwarded_host(true).build(),
+        req(),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://legacy.example/p"));
+
+    let res = call(
+        RedirectSchemeBuilder::new().build(),
+        TestRequest::with_uri("/p").header("x-forwarded-host", "legacy.example"),
+    )
+    .await;
+    assert_eq!(location(&res), Some("https://legacy.example/p"));
+}
+
+#[actix_rt::test]
+async fn scheme_resolver_comes_first() {
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .scheme_resolver(|req| {
+                if req.path().starts_with("/secure") {
+                    Some(SchemeHint::Https)
+                } else {
+                    None
+                }
+            })
+            .build(),
+        TestRequest::with_uri("/secure").header("x-forwarded-proto", "http"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .scheme_resolver(|_| None)
+            .build(),
+        TestRequest::default().header("x-forwarded-proto", "https"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn async_scheme_resolver() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .async_scheme_resolver(|req| {
+                let hint = if req.path() == "/secure" {
+                    Some(SchemeHint::Https)
+                } else {
+                    None
+                };
+                async move { hint }
+            })
+            .build()
+    };
+    let res = call(scheme(), TestRequest::with_uri("/secure")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(scheme(), TestRequest::with_uri("/plain?a=1")).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(location(&res), Some("https://localhost:8080/plain?a=1"));
+}
+
+#[actix_rt::test]
+async fn upgrade_insecure_requests() {
+    let scheme = || {
+        RedirectSchemeBuilder::new()
+            .upgrade_insecure_only(true)
+            .upgrade_insecure_status(StatusCode::TEMPORARY_REDIRECT)
+            .build()
+    };
+    let res = call(
+        scheme(),
+        TestRequest::default().header("upgrade-insecure-requests", "1"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+
+    let res = call(scheme(), TestRequest::default()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = call(
+        RedirectSchemeBuilder::new()
+            .upgrade_insecure_only(true)
+            .insecure_response(StatusCode::FORBIDDEN)
+            .build(),
+        TestRequest::default(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
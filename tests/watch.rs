@@ -0,0 +1,41 @@
+#![cfg(feature = "watch")]
+
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use actix_web::{web, App, HttpResponse};
+use actix_web_middleware_redirect_scheme::WatchedRedirectScheme;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{env, fs, process};
+
+#[actix_rt::test]
+async fn configuration_is_reloaded() {
+    let path = env::temp_dir().join(format!("redirect-scheme-{}.toml", process::id()));
+    fs::write(&path, "temporary = false\n").unwrap();
+    let redirect = WatchedRedirectScheme::new(&path, Duration::from_millis(10)).unwrap();
+    let mut app = test::init_service(
+        App::new()
+            .wrap(redirect.clone())
+            .default_service(web::route().to(HttpResponse::Ok)),
+    )
+    .await;
+    let res = test::call_service(&mut app, TestRequest::with_uri("/p").to_request()).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+    let before = redirect.current();
+    thread::sleep(Duration::from_millis(20));
+    fs::write(&path, "temporary = true\n").unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Arc::ptr_eq(&before, &redirect.current()) && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+    let res = test::call_service(&mut app, TestRequest::with_uri("/p").to_request()).await;
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+
+    fs::write(&path, "unknown = 1\n").unwrap();
+    thread::sleep(Duration::from_millis(100));
+    let res = test::call_service(&mut app, TestRequest::with_uri("/p").to_request()).await;
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+    fs::remove_file(&path).unwrap();
+}